@@ -1,24 +1,64 @@
-use std::{
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{
     alloc::{alloc, dealloc, handle_alloc_error, realloc, Layout},
+    boxed::Box,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    borrow::{Borrow, BorrowMut},
+    cmp::{max, min, Ordering},
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
     marker::PhantomData,
-    mem::{forget, size_of},
-    ops::{Deref, DerefMut},
-    ptr::{copy, read, write, NonNull},
+    mem::{forget, size_of, ManuallyDrop, MaybeUninit},
+    ops::{Bound, Deref, DerefMut, Range, RangeBounds},
+    ptr::{
+        copy, copy_nonoverlapping, drop_in_place, read, slice_from_raw_parts_mut,
+        without_provenance_mut, write, NonNull,
+    },
+    slice,
 };
 
+//////////////// vector! /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+/// Builds a [`Vector`] like `vec!`: `vector![a, b, c]` moves the listed
+/// elements in, and `vector![elem; n]` holds `n` clones of `elem`.
+#[macro_export]
+macro_rules! vector {
+    () => {
+        $crate::Vector::new()
+    };
+    ($elem:expr; $n:expr) => {
+        $crate::Vector::from_elem($elem, $n)
+    };
+    ($($x:expr),+ $(,)?) => {
+        $crate::Vector::from([$($x),+])
+    };
+}
+
 //////////////// Vector /////////////////////////////////
 /////////////////////////////////////////////////////////
 
-struct Vector<T> {
-    buf: RawVec<T>,
+pub struct Vector<T, A: Allocator = Global> {
+    buf: RawVec<T, A>,
     len: usize,
+    shrink: ShrinkPolicy,
 }
 
-#[allow(dead_code)]
-impl<T> Vector<T> {
+impl<T, A: Allocator> Vector<T, A> {
     pub fn push(&mut self, elem: T) {
+        // Growth happens before anything is written and `len` is bumped only
+        // after the write, so if growing panics the vector is untouched and
+        // `elem` is dropped once, as an ordinary argument.
         if self.len == self.capacity() {
-            self.buf.grow();
+            self.buf.grow_amortized(self.len, 1);
         }
 
         unsafe {
@@ -29,6 +69,33 @@ impl<T> Vector<T> {
         self.len += 1;
     }
 
+    // Like `push`, but a failed allocation hands `elem` back along with the
+    // error instead of aborting.
+    pub fn try_push(&mut self, elem: T) -> Result<(), (TryReserveError, T)> {
+        if let Err(e) = self.buf.try_reserve(self.len, 1) {
+            return Err((e, elem));
+        }
+
+        unsafe {
+            write(self.ptr().add(self.len), elem);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    // Like `push`, but hands `elem` back instead of reallocating.
+    pub fn push_within_capacity(&mut self, elem: T) -> Result<(), T> {
+        if self.len == self.capacity() {
+            return Err(elem);
+        }
+
+        unsafe {
+            write(self.ptr().add(self.len), elem);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
     pub fn pop(&mut self) -> Option<T> {
         if self.len == 0 {
             None
@@ -38,16 +105,29 @@ impl<T> Vector<T> {
         }
     }
 
+    pub fn pop_if<F: FnOnce(&mut T) -> bool>(&mut self, predicate: F) -> Option<T> {
+        let last = self.last_mut()?;
+        if predicate(last) {
+            self.pop()
+        } else {
+            None
+        }
+    }
+
     pub fn insert(&mut self, index: usize, elem: T) {
         // Note: `<=` because it's valid to insert after everything
         // which would be equivalent to push.
         assert!(index <= self.len, "index out of bounds");
+        // Growing before anything moves means a failed allocation unwinds
+        // with the vector untouched.
         if self.len == self.capacity() {
-            self.buf.grow();
+            self.buf.grow_amortized(self.len, 1);
         }
+        assert_offset_in_bounds::<T>(index, self.capacity());
 
         unsafe {
             // ptr::copy(src, dest, len): "copy from src to dest len elems"
+            // When `index == len` this copies nothing and it's just a push.
             copy(
                 self.ptr().add(index),
                 self.ptr().add(index + 1),
@@ -59,9 +139,87 @@ impl<T> Vector<T> {
         self.len += 1;
     }
 
+    // Keeps a sorted vector sorted, landing after any equal elements, and
+    // returns where `value` went.
+    pub fn insert_sorted(&mut self, value: T) -> usize
+    where
+        T: Ord,
+    {
+        let index = self.partition_point(|elem| *elem <= value);
+        self.insert(index, value);
+        index
+    }
+
+    // `f` orders each element against `value`, as for `binary_search_by`.
+    // Like `insert_sorted`, `value` lands after any elements equal to it.
+    pub fn binary_insert_by<F: FnMut(&T) -> Ordering>(&mut self, value: T, mut f: F) -> usize {
+        let index = self.partition_point(|elem| f(elem) != Ordering::Greater);
+        self.insert(index, value);
+        index
+    }
+
+    pub fn insert_from_slice(&mut self, index: usize, slice: &[T])
+    where
+        T: Clone,
+    {
+        assert!(index <= self.len, "index out of bounds");
+        let count = slice.len();
+        self.reserve(count);
+
+        // Filled slots are `[index, index + filled)`, the tail sits at
+        // `index + count`. On drop (normal or unwinding from a panicking
+        // clone) the tail is moved back against the filled slots.
+        struct ShiftTailOnDrop<'a, T, A: Allocator> {
+            v: &'a mut Vector<T, A>,
+            index: usize,
+            filled: usize,
+            count: usize,
+            tail_len: usize,
+        }
+
+        impl<T, A: Allocator> Drop for ShiftTailOnDrop<'_, T, A> {
+            fn drop(&mut self) {
+                let end = self.index + self.filled;
+                if self.filled != self.count {
+                    unsafe {
+                        copy(
+                            self.v.ptr().add(self.index + self.count),
+                            self.v.ptr().add(end),
+                            self.tail_len,
+                        );
+                    }
+                }
+                self.v.len = end + self.tail_len;
+            }
+        }
+
+        let tail_len = self.len - index;
+        unsafe {
+            copy(
+                self.ptr().add(index),
+                self.ptr().add(index + count),
+                tail_len,
+            );
+        }
+        self.len = index;
+
+        let mut g = ShiftTailOnDrop {
+            v: self,
+            index,
+            filled: 0,
+            count,
+            tail_len,
+        };
+        for elem in slice {
+            unsafe { write(g.v.ptr().add(g.index + g.filled), elem.clone()) };
+            g.filled += 1;
+        }
+    }
+
     pub fn remove(&mut self, index: usize) -> T {
         // Note: `<` because it's *not* valid to remove after everything
         assert!(index < self.len, "index out of bounds");
+        assert_offset_in_bounds::<T>(index, self.capacity());
         unsafe {
             self.len -= 1;
             let result = read(self.ptr().add(index));
@@ -74,291 +232,4303 @@ impl<T> Vector<T> {
         }
     }
 
-    pub fn drain(&mut self) -> Drain<T> {
-        let iter = unsafe { RawValIter::new(&self) };
+    pub fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let Range { start, end } = slice_range(range, self.len);
 
-        // this is a mem::forget safety thing. If Drain is forgotten, we just
-        // leak the whole Vector's contents. Also we need to do this *eventually*
-        // anyway, so why not do it now?
-        self.len = 0;
+        // Moves the tail down to the new `len`, even when unwinding from a
+        // panicking destructor in the removed range.
+        struct MoveTailOnDrop<'a, T, A: Allocator> {
+            v: &'a mut Vector<T, A>,
+            tail_start: usize,
+            tail_len: usize,
+        }
 
-        Drain {
-            iter,
-            vec: PhantomData,
+        impl<T, A: Allocator> Drop for MoveTailOnDrop<'_, T, A> {
+            fn drop(&mut self) {
+                let start = self.v.len;
+                unsafe {
+                    copy(
+                        self.v.ptr().add(self.tail_start),
+                        self.v.ptr().add(start),
+                        self.tail_len,
+                    );
+                }
+                self.v.len = start + self.tail_len;
+            }
+        }
+
+        let tail_len = self.len - end;
+        // The range and the tail stop belonging to the vector until the
+        // guard runs, so nothing can be dropped twice.
+        self.len = start;
+        let g = MoveTailOnDrop {
+            v: self,
+            tail_start: end,
+            tail_len,
+        };
+        unsafe {
+            drop_in_place(slice_from_raw_parts_mut(g.v.ptr().add(start), end - start));
         }
     }
 
-    fn ptr(&self) -> *mut T {
-        self.buf.ptr.as_ptr()
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        self.retain_mut(|elem| f(elem))
     }
 
-    fn capacity(&self) -> usize {
-        self.buf.capacity
+    // `retain` with the predicate flipped, returning how many were dropped.
+    pub fn remove_all<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> usize {
+        let original_len = self.len;
+        self.retain_mut(|elem| !pred(elem));
+        original_len - self.len
     }
 
-    pub fn new() -> Self {
-        Vector {
-            buf: RawVec::new(),
-            len: 0,
+    pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, mut f: F) {
+        let original_len = self.len;
+        // Until the guard below runs `len` is 0, so if `f` or a destructor
+        // panics we can at worst leak elements, never drop one twice.
+        self.len = 0;
+
+        // Processed-and-kept elements live in `[0, processed - deleted)`,
+        // unprocessed ones in `[processed, original_len)`. On drop (normal
+        // or unwinding) the unprocessed tail is shifted over the hole.
+        struct BackshiftOnDrop<'a, T, A: Allocator> {
+            v: &'a mut Vector<T, A>,
+            processed: usize,
+            deleted: usize,
+            original_len: usize,
+        }
+
+        impl<T, A: Allocator> Drop for BackshiftOnDrop<'_, T, A> {
+            fn drop(&mut self) {
+                if self.deleted > 0 {
+                    unsafe {
+                        copy(
+                            self.v.ptr().add(self.processed),
+                            self.v.ptr().add(self.processed - self.deleted),
+                            self.original_len - self.processed,
+                        );
+                    }
+                }
+                self.v.len = self.original_len - self.deleted;
+            }
+        }
+
+        let mut g = BackshiftOnDrop {
+            v: self,
+            processed: 0,
+            deleted: 0,
+            original_len,
+        };
+
+        while g.processed < original_len {
+            let cur = unsafe { g.v.ptr().add(g.processed) };
+            if !f(unsafe { &mut *cur }) {
+                // Count it as gone before dropping, in case the drop panics.
+                g.processed += 1;
+                g.deleted += 1;
+                unsafe { drop_in_place(cur) };
+                continue;
+            }
+            if g.deleted > 0 {
+                unsafe { copy_nonoverlapping(cur, cur.sub(g.deleted), 1) };
+            }
+            g.processed += 1;
         }
     }
-}
 
-impl<T> Deref for Vector<T> {
-    type Target = [T];
-    fn deref(&self) -> &[T] {
-        unsafe { std::slice::from_raw_parts(self.ptr(), self.len) }
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b)
     }
-}
 
-impl<T> DerefMut for Vector<T> {
-    fn deref_mut(&mut self) -> &mut [T] {
-        unsafe { std::slice::from_raw_parts_mut(self.ptr(), self.len) }
+    pub fn dedup_by_key<K: PartialEq, F: FnMut(&mut T) -> K>(&mut self, mut key: F) {
+        self.dedup_by(|a, b| key(a) == key(b))
     }
-}
 
-impl<T> IntoIterator for Vector<T> {
-    type Item = T;
-    type IntoIter = IntoIter<T>;
-    fn into_iter(self) -> IntoIter<T> {
-        unsafe {
-            let iter = RawValIter::new(&self);
+    /// `same_bucket(a, b)` is called with each element `a` and the last
+    /// kept element `b` before it; returning `true` removes `a`.
+    pub fn dedup_by<F: FnMut(&mut T, &mut T) -> bool>(&mut self, same_bucket: F) {
+        self.dedup_with(same_bucket, drop);
+    }
 
-            let buf = read(&self.buf);
-            forget(self);
+    // Like `dedup`, but the duplicates are handed back in the order they
+    // were found instead of being dropped.
+    pub fn partition_dedup(&mut self) -> Vector<T>
+    where
+        T: PartialEq,
+    {
+        let mut removed = Vector::new();
+        self.dedup_with(|a, b| a == b, |dup| removed.push(dup));
+        removed
+    }
 
-            IntoIter { iter, _buf: buf }
+    // The compaction behind `dedup_by` and `partition_dedup`: each element
+    // `same_bucket` rejects is moved out and passed to `on_duplicate`.
+    fn dedup_with<F, D>(&mut self, mut same_bucket: F, mut on_duplicate: D)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+        D: FnMut(T),
+    {
+        let len = self.len;
+        if len <= 1 {
+            return;
         }
-    }
-}
 
-//////////////// RawVec /////////////////////////////////
-/////////////////////////////////////////////////////////
+        // Kept elements live in `[0, write)`, unexamined ones in
+        // `[read, len)`. On drop (normal or unwinding) the unexamined tail is
+        // shifted down over the hole so nothing is dropped twice.
+        struct FillGapOnDrop<'a, T, A: Allocator> {
+            v: &'a mut Vector<T, A>,
+            read: usize,
+            write: usize,
+            original_len: usize,
+        }
 
-pub struct RawVec<T> {
-    ptr: NonNull<T>,
-    capacity: usize,
-}
+        impl<T, A: Allocator> Drop for FillGapOnDrop<'_, T, A> {
+            fn drop(&mut self) {
+                let remaining = self.original_len - self.read;
+                unsafe {
+                    copy(
+                        self.v.ptr().add(self.read),
+                        self.v.ptr().add(self.write),
+                        remaining,
+                    );
+                }
+                self.v.len = self.write + remaining;
+            }
+        }
 
-impl<T> RawVec<T> {
-    pub fn new() -> Self {
-        assert!(
-            size_of::<T>() != 0,
-            "Cannot allocate memory for zero sized types"
-        );
-        Self {
-            ptr: NonNull::dangling(),
-            capacity: 0usize,
+        // The first element is always kept.
+        let mut g = FillGapOnDrop {
+            v: self,
+            read: 1,
+            write: 1,
+            original_len: len,
+        };
+
+        while g.read < len {
+            unsafe {
+                let read_ptr = g.v.ptr().add(g.read);
+                let prev_ptr = g.v.ptr().add(g.write - 1);
+                if same_bucket(&mut *read_ptr, &mut *prev_ptr) {
+                    // Count it as gone before handing it off, in case
+                    // `on_duplicate` (or the drop) panics.
+                    g.read += 1;
+                    on_duplicate(read(read_ptr));
+                } else {
+                    let write_ptr = g.v.ptr().add(g.write);
+                    // `read_ptr == write_ptr` until the first duplicate.
+                    copy(read_ptr, write_ptr, 1);
+                    g.write += 1;
+                    g.read += 1;
+                }
+            }
         }
     }
 
-    fn grow(&mut self) {
-        let cur_cap_is_zero = || self.capacity == 0;
-        let (new_cap, new_layout) = if cur_cap_is_zero() {
-            (1, Layout::array::<T>(1).unwrap())
-        } else {
-            // This can't overflow since self.cap <= isize::MAX.
-            let new_cap = 2 * self.capacity;
-
-            // `Layout::array` checks that the number of bytes is <= usize::MAX,
-            // but this is redundant since old_layout.size() <= isize::MAX,
-            // so the `unwrap` should never fail.
-            let new_layout = Layout::array::<T>(new_cap).unwrap();
-            (new_cap, new_layout)
-        };
+    pub fn append(&mut self, other: &mut Vector<T, A>) {
+        let count = other.len;
+        self.reserve(count);
+        unsafe {
+            copy_nonoverlapping(other.ptr(), self.ptr().add(self.len), count);
+        }
+        // The elements now belong to `self`; `other` keeps its allocation.
+        other.len = 0;
+        self.len += count;
+    }
 
-        // Ensure that the new allocation doesn't exceed `isize::MAX` bytes.
-        assert!(
-            new_layout.size() <= isize::MAX as usize,
-            "Allocation too large"
-        );
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
 
-        let new_ptr = if cur_cap_is_zero() {
-            unsafe { alloc(new_layout) }
-        } else {
-            let old_layout = Layout::array::<T>(self.capacity).unwrap();
-            let old_ptr = self.ptr.as_ptr() as *mut u8;
-            unsafe { realloc(old_ptr, old_layout, new_layout.size()) }
-        };
+        let tail = slice_from_raw_parts_mut(unsafe { self.ptr().add(len) }, self.len - len);
+        // Shorten first so a panicking destructor can't lead to a double drop.
+        self.len = len;
+        unsafe { drop_in_place(tail) };
+    }
 
-        // If allocation fails, `new_ptr` will be null, in which case we abort.
-        self.ptr = match NonNull::new(new_ptr as *mut T) {
-            Some(p) => p,
-            None => handle_alloc_error(new_layout),
-        };
-        self.capacity = new_cap;
+    pub fn resize(&mut self, new_len: usize, value: T)
+    where
+        T: Clone,
+    {
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return;
+        }
+
+        let extra = new_len - self.len;
+        self.reserve(extra);
+        for _ in 1..extra {
+            self.push(value.clone());
+        }
+        // The last slot takes `value` itself, saving a clone.
+        self.push(value);
     }
-}
 
-unsafe impl<T: Send> Send for RawVec<T> {}
-unsafe impl<T: Sync> Sync for RawVec<T> {}
+    pub fn resize_with<F: FnMut() -> T>(&mut self, new_len: usize, mut f: F) {
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return;
+        }
 
-impl<T> Drop for RawVec<T> {
-    fn drop(&mut self) {
-        if self.capacity != 0 {
-            let layout = Layout::array::<T>(self.capacity).unwrap();
-            unsafe {
-                dealloc(self.ptr.as_ptr() as *mut u8, layout);
-            }
+        self.reserve(new_len - self.len);
+        while self.len < new_len {
+            self.push(f());
         }
     }
-}
 
-//////////////// IntoIter /////////////////////////////////
-/////////////////////////////////////////////////////////
+    pub fn resize_default(&mut self, new_len: usize)
+    where
+        T: Default,
+    {
+        self.resize_with(new_len, T::default);
+    }
 
-pub struct IntoIter<T> {
-    _buf: RawVec<T>, // we don't actually care about this. Just need it to live.
-    iter: RawValIter<T>,
-}
+    pub fn extend_from_slice(&mut self, other: &[T])
+    where
+        T: Clone,
+    {
+        self.reserve(other.len());
+        for elem in other {
+            self.push(elem.clone());
+        }
+    }
 
-impl<T> Iterator for IntoIter<T> {
-    type Item = T;
-    fn next(&mut self) -> Option<T> {
-        self.iter.next()
+    // One allocation, then the filled prefix is copied onto the end of
+    // itself, doubling each time, until the last partial copy.
+    pub fn repeat(&self, n: usize) -> Vector<T>
+    where
+        T: Copy,
+    {
+        let total = self.len.checked_mul(n).expect("capacity overflow");
+        let mut v = Vector::with_capacity(total);
+        if total == 0 {
+            return v;
+        }
+
+        v.extend_from_copy_slice(self);
+        while v.len < total {
+            let count = min(v.len, total - v.len);
+            unsafe {
+                copy_nonoverlapping(v.ptr(), v.ptr().add(v.len), count);
+            }
+            v.len += count;
+        }
+        v
     }
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+
+    // One reservation and one bulk copy, for byte buffers and the like.
+    pub fn extend_from_copy_slice(&mut self, other: &[T])
+    where
+        T: Copy,
+    {
+        let count = other.len();
+        self.reserve(count);
+        unsafe {
+            copy_nonoverlapping(other.as_ptr(), self.ptr().add(self.len), count);
+        }
+        self.len += count;
     }
-}
 
-impl<T> DoubleEndedIterator for IntoIter<T> {
-    fn next_back(&mut self) -> Option<T> {
-        self.iter.next_back()
+    pub fn fill(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        if let Some((last, rest)) = self.split_last_mut() {
+            for slot in rest {
+                *slot = value.clone();
+            }
+            *last = value;
+        }
     }
-}
 
-impl<T> Drop for IntoIter<T> {
-    fn drop(&mut self) {
-        for _ in &mut *self {}
+    pub fn fill_with<F: FnMut() -> T>(&mut self, mut f: F) {
+        for slot in self.iter_mut() {
+            *slot = f();
+        }
     }
-}
 
-//////////////// Drain /////////////////////////////////
-/////////////////////////////////////////////////////////
+    pub fn reserve(&mut self, additional: usize) {
+        handle_reserve(self.try_reserve(additional));
+    }
 
-pub struct Drain<'a, T: 'a> {
-    vec: PhantomData<&'a mut Vector<T>>,
-    iter: RawValIter<T>,
-}
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if self.capacity() - self.len >= additional {
+            return;
+        }
 
-impl<'a, T> Iterator for Drain<'a, T> {
-    type Item = T;
-    fn next(&mut self) -> Option<T> {
-        self.iter.next()
+        let result = match self.len.checked_add(additional) {
+            Some(required) => self.buf.grow_to(required),
+            None => Err(TryReserveError::CapacityOverflow),
+        };
+        handle_reserve(result);
     }
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.buf.try_reserve(self.len, additional)
     }
-}
 
-impl<'a, T> DoubleEndedIterator for Drain<'a, T> {
-    fn next_back(&mut self) -> Option<T> {
-        self.iter.next_back()
+    pub fn shrink_to_fit(&mut self) {
+        if self.capacity() > self.len {
+            self.buf.shrink_to(self.len);
+        }
     }
-}
 
-impl<'a, T> Drop for Drain<'a, T> {
-    fn drop(&mut self) {
-        for _ in &mut *self {}
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let new_cap = max(self.len, min_capacity);
+        if self.capacity() > new_cap {
+            self.buf.shrink_to(new_cap);
+        }
     }
-}
 
-//////////////// RawValIter /////////////////////////////////
-/////////////////////////////////////////////////////////
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T, A> {
+        let orig_len = self.len;
+        let Range { start, end } = slice_range(range, orig_len);
+        let tail_len = orig_len - end;
 
-struct RawValIter<T> {
-    start: *const T,
-    end: *const T,
-}
+        // this is a mem::forget safety thing. If Drain is forgotten, we just
+        // leak the drained range and the tail. Drain's destructor puts the
+        // tail back once the range has been dropped.
+        self.len = start;
 
-impl<T> RawValIter<T> {
-    // unsafe to construct because it has no associated lifetimes.
-    // This is necessary to store a RawValIter in the same struct as
-    // its actual allocation. OK since it's a private implementation
-    // detail.
-    unsafe fn new(slice: &[T]) -> Self {
-        RawValIter {
-            start: slice.as_ptr(),
-            end: if slice.len() == 0 {
-                // if `len = 0`, then this is not actually allocated memory.
-                // Need to avoid offsetting because that will give wrong
-                // information to LLVM via GEP.
-                slice.as_ptr()
-            } else {
-                slice.as_ptr().add(slice.len())
-            },
+        let iter = unsafe { RawValIter::from_raw_parts(self.ptr().add(start), end - start) };
+
+        Drain {
+            iter,
+            vec: NonNull::from(self),
+            tail_start: end,
+            tail_len,
+            orig_len,
+            marker: PhantomData,
         }
     }
-}
 
-impl<T> DoubleEndedIterator for RawValIter<T> {
-    fn next_back(&mut self) -> Option<T> {
-        if self.start == self.end {
-            None
-        } else {
-            unsafe {
-                if size_of::<T>() == 0 {
-                    self.end = (self.end as usize - 1) as *const _;
-                    Some(read(NonNull::<T>::dangling().as_ptr()))
-                } else {
-                    self.end = self.end.offset(-1);
-                    Some(read(self.end))
-                }
-            }
+    // Yields everything in ascending order, leaving the vector empty.
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, T, A>
+    where
+        T: Ord,
+    {
+        self.sort();
+        DrainSorted {
+            drain: self.drain(..),
         }
     }
-}
 
-impl<T> Iterator for RawValIter<T> {
-    type Item = T;
-    fn next(&mut self) -> Option<T> {
-        if self.start == self.end {
-            None
-        } else {
-            unsafe {
-                if size_of::<T>() == 0 {
-                    self.start = (self.start as usize + 1) as *const _;
-                    Some(read(NonNull::<T>::dangling().as_ptr()))
-                } else {
-                    let old_ptr = self.start;
-                    self.start = self.start.offset(1);
-                    Some(read(old_ptr))
-                }
-            }
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> Splice<'_, T, I::IntoIter, A>
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = T>,
+    {
+        Splice {
+            drain: self.drain(range),
+            replace_with: replace_with.into_iter(),
         }
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let elem_size = size_of::<T>();
-        let len =
-            (self.end as usize - self.start as usize) / if elem_size == 0 { 1 } else { elem_size };
-        (len, Some(len))
+    // An empty splice: reserves by the `size_hint` lower bound, buffers
+    // whatever comes past it, and shifts the tail as few times as it can.
+    pub fn insert_many<I: IntoIterator<Item = T>>(&mut self, index: usize, iter: I) {
+        assert!(index <= self.len, "index out of bounds");
+        self.splice(index..index, iter);
     }
-}
+
+    // `retain`, but the rejected elements are handed back in order.
+    pub fn retain_take<F: FnMut(&T) -> bool>(&mut self, mut keep: F) -> Vector<T> {
+        self.extract_if(|elem| !keep(elem)).collect()
+    }
+
+    pub fn extract_if<F: FnMut(&mut T) -> bool>(&mut self, filter: F) -> ExtractIf<'_, T, F, A> {
+        let old_len = self.len;
+        // Same leak amplification as `drain`: if the iterator is forgotten
+        // the vector just looks empty.
+        self.len = 0;
+
+        ExtractIf {
+            vec: self,
+            idx: 0,
+            del: 0,
+            old_len,
+            pred: filter,
+            panic_flag: false,
+        }
+    }
+
+    pub fn leak<'a>(mut self) -> &'a mut [T]
+    where
+        A: 'a,
+    {
+        self.shrink_to_fit();
+        let slice = slice_from_raw_parts_mut(self.ptr(), self.len);
+        // Never freed: the buffer (and the allocator) live for the rest of
+        // the program.
+        forget(self);
+        unsafe { &mut *slice }
+    }
+
+    fn ptr(&self) -> *mut T {
+        self.buf.ptr()
+    }
+
+    /// Sets the length without touching the elements.
+    ///
+    /// # Safety
+    ///
+    /// * `new_len` must be at most [`capacity`](Vector::capacity).
+    /// * The elements in `[old_len, new_len)` must be initialized.
+    /// * When shrinking, the elements in `[new_len, old_len)` are no longer
+    ///   dropped by the vector; the caller is responsible for them.
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.capacity());
+        self.len = new_len;
+    }
+
+    // The uninitialized slots `[len, capacity)`. Fill them, then `set_len`.
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.ptr().add(self.len) as *mut MaybeUninit<T>,
+                self.capacity() - self.len,
+            )
+        }
+    }
+
+    // Valid for `capacity` elements, dangling when nothing is allocated.
+    pub fn as_ptr(&self) -> *const T {
+        self.buf.ptr()
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.buf.ptr()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        self
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    // Runs of consecutive equal elements, one slice per run.
+    pub fn group_runs(&self) -> GroupRuns<'_, T>
+    where
+        T: PartialEq,
+    {
+        GroupRuns {
+            rest: self.as_slice(),
+        }
+    }
+
+    // `[T; N]` has the same alignment as `T`, so the front of the buffer can
+    // be viewed as whole arrays. Panics if `N` is 0.
+    pub fn as_chunks<const N: usize>(&self) -> (&[[T; N]], &[T]) {
+        self.as_slice().as_chunks()
+    }
+
+    pub fn as_chunks_mut<const N: usize>(&mut self) -> (&mut [[T; N]], &mut [T]) {
+        self.as_mut_slice().as_chunks_mut()
+    }
+
+    // Panics if `size` is 0.
+    pub fn windows(&self, size: usize) -> slice::Windows<'_, T> {
+        self.as_slice().windows(size)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.as_mut_slice().get_mut(index)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.as_slice().first()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.as_slice().last()
+    }
+
+    pub fn first_mut(&mut self) -> Option<&mut T> {
+        self.as_mut_slice().first_mut()
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.as_mut_slice().last_mut()
+    }
+
+    // Panics if either index is out of bounds.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.as_mut_slice().swap(a, b)
+    }
+
+    /// Swaps every element with the one at the same index in `other`, e.g.
+    /// to flip a pair of double buffers.
+    ///
+    /// Panics if `other.len() != self.len()`.
+    pub fn swap_with_slice(&mut self, other: &mut [T]) {
+        self.as_mut_slice().swap_with_slice(other)
+    }
+
+    pub fn contains(&self, x: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.as_slice().contains(x)
+    }
+
+    /// Whether the vector begins with `needle`, e.g. to check a protocol
+    /// prefix on a byte buffer. An empty `needle` always matches.
+    ///
+    /// ```
+    /// use vector::vector;
+    ///
+    /// let packet = vector![b'G', b'E', b'T', b' ', b'/'];
+    /// assert!(packet.starts_with(b"GET "));
+    /// assert!(!packet.starts_with(b"POST "));
+    /// ```
+    pub fn starts_with(&self, needle: &[T]) -> bool
+    where
+        T: PartialEq,
+    {
+        self.as_slice().starts_with(needle)
+    }
+
+    /// Whether the vector ends with `needle`, e.g. a `b"\r\n"` terminator.
+    /// An empty `needle` always matches.
+    pub fn ends_with(&self, needle: &[T]) -> bool
+    where
+        T: PartialEq,
+    {
+        self.as_slice().ends_with(needle)
+    }
+
+    /// Rotates the elements so the one at `mid` comes first. Works in place:
+    /// O(len) moves and no allocation. Panics if `mid > len`.
+    pub fn rotate_left(&mut self, mid: usize) {
+        self.as_mut_slice().rotate_left(mid)
+    }
+
+    /// Rotates the elements so the last `k` come first. Works in place:
+    /// O(len) moves and no allocation. Panics if `k > len`.
+    pub fn rotate_right(&mut self, k: usize) {
+        self.as_mut_slice().rotate_right(k)
+    }
+
+    pub fn reverse(&mut self) {
+        self.as_mut_slice().reverse()
+    }
+
+    // The slice sorts keep every element in place exactly once even if the
+    // comparator panics, so the vector needs no guard of its own.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.as_mut_slice().sort()
+    }
+
+    pub fn sort_unstable(&mut self)
+    where
+        T: Ord,
+    {
+        self.as_mut_slice().sort_unstable()
+    }
+
+    // Equal elements are adjacent once sorted, so a single `==` pass over
+    // neighbours finishes the job.
+    pub fn unique_sorted(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_unstable();
+        self.dedup();
+    }
+
+    pub fn sort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, compare: F) {
+        self.as_mut_slice().sort_by(compare)
+    }
+
+    pub fn sort_by_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F) {
+        self.as_mut_slice().sort_by_key(f)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    // Bytes owned by the buffer, whether or not they hold elements.
+    pub fn allocated_bytes(&self) -> usize {
+        self.buf.capacity * size_of::<T>()
+    }
+
+    // The layout the buffer was allocated with, or `None` if nothing is
+    // allocated.
+    pub fn current_layout(&self) -> Option<Layout> {
+        if self.buf.capacity == 0 {
+            None
+        } else {
+            RawVec::<T, A>::array_layout(self.buf.capacity).ok()
+        }
+    }
+
+    pub fn new_in(alloc: A) -> Self {
+        Vector {
+            buf: RawVec::new_in(alloc),
+            len: 0,
+            shrink: ShrinkPolicy::Never,
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Vector {
+            buf: RawVec::with_capacity_in(capacity, alloc),
+            len: 0,
+            shrink: ShrinkPolicy::Never,
+        }
+    }
+
+    /// Rebuilds a `Vector` from the pointer, length and capacity of one that
+    /// was previously taken apart, along with the allocator that owns it.
+    ///
+    /// # Safety
+    ///
+    /// * `ptr` must have been allocated by `alloc` with the layout of
+    ///   `[T; capacity]`, or be dangling if `capacity` is 0.
+    /// * `length <= capacity`, and the first `length` elements must be
+    ///   initialized values of `T`.
+    /// * Ownership of the allocation moves to the returned `Vector`; nothing
+    ///   else may use or free it afterwards.
+    pub unsafe fn from_raw_parts_in(ptr: *mut T, length: usize, capacity: usize, alloc: A) -> Self {
+        let mut buf = RawVec::new_in(alloc);
+        buf.ptr = NonNull::new_unchecked(ptr);
+        buf.capacity = capacity;
+        Vector {
+            buf,
+            len: length,
+            shrink: ShrinkPolicy::Never,
+        }
+    }
+
+    pub fn map<U, F: FnMut(T) -> U>(self, mut f: F) -> Vector<U> {
+        let mut mapped = Vector::with_capacity(self.len);
+        for elem in self {
+            mapped.push(f(elem));
+        }
+        mapped
+    }
+
+    pub fn filter_map<U, F: FnMut(T) -> Option<U>>(self, mut f: F) -> Vector<U> {
+        let mut kept = Vector::new();
+        for elem in self {
+            if let Some(elem) = f(elem) {
+                kept.push(elem);
+            }
+        }
+        kept
+    }
+
+    // Each chunk owns its elements; the last one may be short.
+    pub fn into_chunks(self, n: usize) -> IntoChunks<T, A> {
+        assert!(n != 0, "chunk size must be non-zero");
+        IntoChunks {
+            iter: self.into_iter(),
+            size: n,
+        }
+    }
+
+    pub fn set_shrink_policy(&mut self, policy: ShrinkPolicy) {
+        self.shrink = policy;
+    }
+
+    // Called once a removal has shortened the vector and settled `len`.
+    fn apply_shrink_policy(&mut self) {
+        match self.shrink {
+            ShrinkPolicy::Never => {}
+            // Shrinking to twice `len` leaves room to grow again without
+            // reallocating straight away.
+            ShrinkPolicy::Quarter if self.len < self.capacity() / 4 => {
+                self.shrink_to(self.len * 2);
+            }
+            ShrinkPolicy::Quarter => {}
+        }
+    }
+
+    pub fn allocator(&self) -> &A {
+        &self.buf.alloc
+    }
+}
+
+impl<T> Vector<T> {
+    pub fn new() -> Self {
+        Vector::new_in(Global)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Vector::with_capacity_in(capacity, Global)
+    }
+
+    // `n` clones of `elem` in a single allocation, the last slot taking
+    // `elem` itself.
+    pub fn from_elem(elem: T, n: usize) -> Self
+    where
+        T: Clone,
+    {
+        let mut v = Vector::with_capacity(n);
+        v.resize(n, elem);
+        v
+    }
+
+    pub fn with_growth(growth: GrowthStrategy) -> Self {
+        let mut v = Vector::new();
+        v.buf.growth = growth;
+        v
+    }
+
+    /// Rebuilds a `Vector` from the pointer, length and capacity of one that
+    /// was previously taken apart.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`from_raw_parts_in`](Vector::from_raw_parts_in), with the
+    /// buffer allocated by the global allocator.
+    pub unsafe fn from_raw_parts(ptr: *mut T, length: usize, capacity: usize) -> Self {
+        Vector::from_raw_parts_in(ptr, length, capacity, Global)
+    }
+
+    pub fn into_boxed_slice(mut self) -> Box<[T]> {
+        self.shrink_to_fit();
+        // The buffer is now exactly `len` elements long, which is the layout
+        // `Box<[T]>` expects to free it with.
+        let slice = slice_from_raw_parts_mut(self.ptr(), self.len);
+        forget(self);
+        unsafe { Box::from_raw(slice) }
+    }
+
+    /// Like [`map`](Vector::map), but when `T` and `U` share a layout the
+    /// results are written back over the originals and the allocation is
+    /// reused. Otherwise this falls back to `map`.
+    pub fn map_in_place<U, F: FnMut(T) -> U>(self, mut f: F) -> Vector<U> {
+        if Layout::new::<T>() != Layout::new::<U>() {
+            return self.map(f);
+        }
+
+        // Slots `[0, mapped)` hold `U`s and `[mapped, len)` still hold `T`s.
+        // `f` may panic after taking the element at `mapped`, so on unwind
+        // the guard drops both halves around it and frees the buffer.
+        struct MapGuard<T, U> {
+            ptr: *mut T,
+            len: usize,
+            capacity: usize,
+            mapped: usize,
+            marker: PhantomData<U>,
+        }
+
+        impl<T, U> Drop for MapGuard<T, U> {
+            fn drop(&mut self) {
+                unsafe {
+                    drop_in_place(slice_from_raw_parts_mut(self.ptr as *mut U, self.mapped));
+                    let rest = self.ptr.add(self.mapped + 1);
+                    drop_in_place(slice_from_raw_parts_mut(rest, self.len - self.mapped - 1));
+                    drop(Vector::from_raw_parts(self.ptr as *mut U, 0, self.capacity));
+                }
+            }
+        }
+
+        let v = ManuallyDrop::new(self);
+        let (growth, shrink) = (v.buf.growth, v.shrink);
+        let mut g = MapGuard::<T, U> {
+            ptr: v.ptr(),
+            len: v.len,
+            capacity: v.capacity(),
+            mapped: 0,
+            marker: PhantomData,
+        };
+
+        // Same size and alignment, so a `U` fits exactly where each `T` was
+        // read from, and the buffer's layout is valid as `[U; capacity]`.
+        while g.mapped < g.len {
+            unsafe {
+                let slot = g.ptr.add(g.mapped);
+                let elem = f(read(slot));
+                write(slot as *mut U, elem);
+            }
+            g.mapped += 1;
+        }
+
+        let g = ManuallyDrop::new(g);
+        let mut mapped = unsafe { Vector::from_raw_parts(g.ptr as *mut U, g.len, g.capacity) };
+        mapped.buf.growth = growth;
+        mapped.shrink = shrink;
+        mapped
+    }
+
+    /// Hands the buffer over to a `Vec` without copying or reallocating:
+    /// both use the global allocator and a `[T; capacity]` layout, so the
+    /// pointer, length and capacity carry over unchanged.
+    ///
+    /// Zero-sized element types never reach this point, since `RawVec`
+    /// refuses to build a buffer for them.
+    pub fn into_vec(self) -> Vec<T> {
+        let v = ManuallyDrop::new(self);
+        unsafe { Vec::from_raw_parts(v.ptr(), v.len, v.capacity()) }
+    }
+
+    pub fn into_shared(self) -> SharedVector<T> {
+        SharedVector {
+            elems: Arc::from(self.into_boxed_slice()),
+        }
+    }
+
+    // The rest keeps the original buffer, shifted down by one.
+    pub fn split_first_owned(mut self) -> Option<(T, Vector<T>)> {
+        if self.is_empty() {
+            return None;
+        }
+        let first = self.remove(0);
+        Some((first, self))
+    }
+
+    pub fn split_last_owned(mut self) -> Option<(T, Vector<T>)> {
+        let last = self.pop()?;
+        Some((last, self))
+    }
+
+    // The front half keeps the original buffer; the back half is moved
+    // into a fresh allocation of its own.
+    pub fn split_at_owned(mut self, mid: usize) -> (Vector<T>, Vector<T>) {
+        assert!(mid <= self.len, "mid out of bounds");
+        let tail_len = self.len - mid;
+        let mut tail = Vector::with_capacity(tail_len);
+        unsafe {
+            // Give up ownership of the tail before it's duplicated.
+            self.len = mid;
+            copy_nonoverlapping(self.ptr().add(mid), tail.ptr(), tail_len);
+            tail.len = tail_len;
+        }
+        (self, tail)
+    }
+}
+
+impl<T: Clone, A: Allocator, B: Allocator> Vector<Vector<T, B>, A> {
+    pub fn concat(&self) -> Vector<T> {
+        let total = self.iter().map(|inner| inner.len).sum();
+        let mut v = Vector::with_capacity(total);
+        for inner in self {
+            v.extend_from_slice(inner);
+        }
+        v
+    }
+}
+
+impl<T, A: Allocator + Default> Default for Vector<T, A> {
+    fn default() -> Self {
+        Vector::new_in(A::default())
+    }
+}
+
+impl<T, A: Allocator> Drop for Vector<T, A> {
+    fn drop(&mut self) {
+        // RawVec only frees the buffer, so the elements have to be
+        // dropped here first.
+        while self.pop().is_some() {}
+    }
+}
+
+impl<T, A: Allocator> Deref for Vector<T, A> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        unsafe { core::slice::from_raw_parts(self.ptr(), self.len) }
+    }
+}
+
+impl<T, A: Allocator> DerefMut for Vector<T, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr(), self.len) }
+    }
+}
+
+impl<T, A: Allocator> AsRef<[T]> for Vector<T, A> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, A: Allocator> AsMut<[T]> for Vector<T, A> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, A: Allocator> Borrow<[T]> for Vector<T, A> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, A: Allocator> BorrowMut<[T]> for Vector<T, A> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T: fmt::Debug, A: Allocator> fmt::Debug for Vector<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, U, A1, A2> PartialEq<Vector<U, A2>> for Vector<T, A1>
+where
+    T: PartialEq<U>,
+    A1: Allocator,
+    A2: Allocator,
+{
+    fn eq(&self, other: &Vector<U, A2>) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: Eq, A: Allocator> Eq for Vector<T, A> {}
+
+impl<T: PartialEq<U>, U, A: Allocator> PartialEq<[U]> for Vector<T, A> {
+    fn eq(&self, other: &[U]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, A: Allocator> PartialEq<&[U]> for Vector<T, A> {
+    fn eq(&self, other: &&[U]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, A: Allocator, const N: usize> PartialEq<[U; N]> for Vector<T, A> {
+    fn eq(&self, other: &[U; N]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialOrd, A: Allocator> PartialOrd for Vector<T, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self[..].partial_cmp(&other[..])
+    }
+}
+
+impl<T: Ord, A: Allocator> Ord for Vector<T, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self[..].cmp(&other[..])
+    }
+}
+
+impl<T: Hash, A: Allocator> Hash for Vector<T, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self[..].hash(state)
+    }
+}
+
+impl<T, A: Allocator> IntoIterator for Vector<T, A> {
+    type Item = T;
+    type IntoIter = IntoIter<T, A>;
+    fn into_iter(self) -> IntoIter<T, A> {
+        unsafe {
+            let iter = RawValIter::from_raw_parts(self.ptr(), self.len);
+
+            let buf = read(&self.buf);
+            forget(self);
+
+            IntoIter { iter, _buf: buf }
+        }
+    }
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a Vector<T, A> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a mut Vector<T, A> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T, A: Allocator> Extend<T> for Vector<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        self.reserve(lower);
+        while let Some(elem) = iter.next() {
+            if self.len == self.capacity() {
+                // The hint was short; ask again for what is left.
+                let (lower, _) = iter.size_hint();
+                self.buf.grow_amortized(self.len, lower.saturating_add(1));
+            }
+            unsafe { write(self.ptr().add(self.len), elem) };
+            self.len += 1;
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Vector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = Vector::new();
+        v.extend(iter);
+        v
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Vector<T> {
+    fn from(array: [T; N]) -> Self {
+        let mut v = Vector::with_capacity(N);
+        // The elements are moved into the vector, so the array must not
+        // drop them.
+        let array = ManuallyDrop::new(array);
+        unsafe {
+            copy_nonoverlapping(array.as_ptr(), v.ptr(), N);
+        }
+        v.len = N;
+        v
+    }
+}
+
+impl<T: Clone> From<&[T]> for Vector<T> {
+    fn from(slice: &[T]) -> Self {
+        let mut v = Vector::with_capacity(slice.len());
+        v.extend_from_slice(slice);
+        v
+    }
+}
+
+impl<T> From<Vec<T>> for Vector<T> {
+    fn from(vec: Vec<T>) -> Self {
+        // Both sides use the global allocator with `[T; capacity]` layouts,
+        // so the buffer can change hands as is.
+        let mut vec = ManuallyDrop::new(vec);
+        unsafe { Vector::from_raw_parts(vec.as_mut_ptr(), vec.len(), vec.capacity()) }
+    }
+}
+
+impl<T> From<Vector<T>> for Vec<T> {
+    fn from(vector: Vector<T>) -> Self {
+        vector.into_vec()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A: Allocator> std::io::Write for Vector<u8, A> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.extend_from_copy_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A: Allocator> Vector<u8, A> {
+    // Reads until EOF straight into the spare capacity and returns how many
+    // bytes were appended. Bytes read before an error are kept.
+    pub fn extend_from_reader<R: std::io::Read>(
+        &mut self,
+        reader: &mut R,
+    ) -> std::io::Result<usize> {
+        let start = self.len;
+        // How many bytes past `len` are already zeroed. Growing preserves the
+        // old buffer, so only newly reserved bytes ever need zeroing; doing
+        // the whole spare region per `read` would be quadratic for readers
+        // that return small chunks.
+        let mut zeroed = 0;
+        loop {
+            if self.len == self.capacity() {
+                self.reserve(32);
+            }
+
+            let spare = self.spare_capacity_mut();
+            // `Read` is allowed to look at the buffer, so it can't be uninitialized.
+            spare[zeroed..].fill(MaybeUninit::new(0));
+            zeroed = spare.len();
+            let spare =
+                unsafe { slice::from_raw_parts_mut(spare.as_mut_ptr() as *mut u8, spare.len()) };
+            let available = spare.len();
+
+            match reader.read(spare) {
+                Ok(0) => return Ok(self.len - start),
+                Ok(n) => {
+                    assert!(
+                        n <= available,
+                        "reader claimed more bytes than it was given"
+                    );
+                    unsafe { self.set_len(self.len + n) };
+                    zeroed -= n;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, A: Allocator> Vector<T, A> {
+    // Removes every repeat, wherever it is, keeping first occurrences in
+    // their original order.
+    pub fn dedup_total(&mut self)
+    where
+        T: Eq + Hash,
+    {
+        // The set borrows the elements, so decide everything up front and
+        // let `retain` do the moving.
+        let mut seen = std::collections::HashSet::with_capacity(self.len);
+        let keep: Vec<bool> = self.iter().map(|elem| seen.insert(elem)).collect();
+        drop(seen);
+
+        let mut keep = keep.into_iter();
+        self.retain(|_| keep.next().unwrap());
+    }
+}
+
+// Largest byte offset `insert` and `remove` may compute from the buffer
+// start. Tests lower it so the check can be hit without a huge allocation.
+#[cfg(not(test))]
+const MAX_OFFSET_BYTES: usize = isize::MAX as usize;
+#[cfg(test)]
+const MAX_OFFSET_BYTES: usize = 1 << 20;
+
+// Checks that the slot after `index` is still inside the buffer and within
+// `MAX_OFFSET_BYTES`, so `ptr().add(index + 1)` can't overflow.
+fn assert_offset_in_bounds<T>(index: usize, capacity: usize) {
+    let in_bounds = index < capacity
+        && (index + 1)
+            .checked_mul(size_of::<T>())
+            .is_some_and(|bytes| bytes <= MAX_OFFSET_BYTES);
+    assert!(in_bounds, "offset of index {index} overflows the buffer");
+}
+
+// Resolves `range` against a sequence of length `len`, panicking if it is
+// out of bounds like slice indexing does.
+fn slice_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start
+            .checked_add(1)
+            .expect("attempted to index slice from after maximum usize"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end
+            .checked_add(1)
+            .expect("attempted to index slice up to maximum usize"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+
+    assert!(
+        start <= end,
+        "slice index starts at {start} but ends at {end}"
+    );
+    assert!(
+        end <= len,
+        "range end index {end} out of range for slice of length {len}"
+    );
+    start..end
+}
+
+//////////////// RawVec /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+pub struct RawVec<T, A: Allocator = Global> {
+    ptr: NonNull<T>,
+    capacity: usize,
+    growth: GrowthStrategy,
+    alloc: A,
+}
+
+impl<T> RawVec<T> {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_in(capacity, Global)
+    }
+}
+
+impl<T, A: Allocator> RawVec<T, A> {
+    pub fn new_in(alloc: A) -> Self {
+        assert!(
+            size_of::<T>() != 0,
+            "Cannot allocate memory for zero sized types"
+        );
+        Self {
+            ptr: NonNull::dangling(),
+            capacity: 0usize,
+            growth: GrowthStrategy::Double,
+            alloc,
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        let mut buf = Self::new_in(alloc);
+        if capacity != 0 {
+            handle_reserve(buf.grow_to(capacity));
+        }
+        buf
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    // Layout of a buffer holding `capacity` elements, refusing anything
+    // over `isize::MAX` bytes. `Layout::array` does the checked
+    // `capacity * size_of::<T>()` multiplication.
+    fn array_layout(capacity: usize) -> Result<Layout, TryReserveError> {
+        match Layout::array::<T>(capacity) {
+            Ok(layout) if layout.size() <= isize::MAX as usize => Ok(layout),
+            _ => Err(TryReserveError::CapacityOverflow),
+        }
+    }
+
+    // The capacity one step of amortized growth moves to. A valid buffer is
+    // at most `isize::MAX` bytes so this shouldn't overflow, but `capacity`
+    // is only trusted as far as the checked arithmetic goes.
+    fn next_capacity(&self) -> Result<usize, TryReserveError> {
+        let cap = self.capacity;
+        let next = match self.growth {
+            GrowthStrategy::Double if cap == 0 => Some(1),
+            GrowthStrategy::Double => cap.checked_mul(2),
+            // Both grow by at least one element, so a capacity below 2 (or a
+            // `Fixed(0)` step) still makes progress.
+            GrowthStrategy::OnePointFive => cap.checked_add(max(cap / 2, 1)),
+            GrowthStrategy::Fixed(step) => cap.checked_add(max(step, 1)),
+        };
+        next.ok_or(TryReserveError::CapacityOverflow)
+    }
+
+    // Makes room for `additional` more elements past the first `len`.
+    fn try_reserve(&mut self, len: usize, additional: usize) -> Result<(), TryReserveError> {
+        if self.capacity - len >= additional {
+            return Ok(());
+        }
+        self.try_grow_amortized(len, additional)
+    }
+
+    fn grow_amortized(&mut self, len: usize, additional: usize) {
+        handle_reserve(self.try_grow_amortized(len, additional));
+    }
+
+    // Grows straight to whichever is larger: what the caller needs, or one
+    // step of the growth strategy. Jumping to the target in one allocation
+    // keeps big reserves and pushes after a short `size_hint` from
+    // reallocating repeatedly.
+    fn try_grow_amortized(&mut self, len: usize, additional: usize) -> Result<(), TryReserveError> {
+        let required = len
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+        self.grow_to(max(required, self.next_capacity()?))
+    }
+
+    // One step of the growth strategy, for callers that just found the
+    // buffer full. `Vector` itself goes through `grow_amortized`; this is
+    // for other containers built on `RawVec`.
+    #[allow(dead_code)]
+    pub(crate) fn grow(&mut self) {
+        handle_reserve(self.next_capacity().and_then(|cap| self.grow_to(cap)));
+    }
+
+    // Reallocates the buffer so it holds exactly `new_cap` elements. On
+    // failure the buffer is left untouched.
+    pub(crate) fn grow_to(&mut self, new_cap: usize) -> Result<(), TryReserveError> {
+        debug_assert!(new_cap > self.capacity, "grow_to must not shrink");
+
+        // Ensure that the new allocation doesn't exceed `isize::MAX` bytes.
+        let new_layout = Self::array_layout(new_cap)?;
+
+        let result = if self.capacity == 0 {
+            self.alloc.allocate(new_layout)
+        } else {
+            let old_layout = Layout::array::<T>(self.capacity).unwrap();
+            unsafe { self.alloc.grow(self.ptr.cast(), old_layout, new_layout) }
+        };
+
+        // If allocation fails the old buffer (if any) is still valid.
+        self.ptr = match result {
+            Ok(p) => p.cast(),
+            Err(AllocError) => return Err(TryReserveError::AllocError { layout: new_layout }),
+        };
+        self.capacity = new_cap;
+        Ok(())
+    }
+
+    // Reallocates the buffer down to exactly `new_cap` elements, freeing it
+    // entirely when `new_cap` is zero.
+    fn shrink_to(&mut self, new_cap: usize) {
+        debug_assert!(new_cap < self.capacity, "shrink_to must not grow");
+
+        let old_layout = Layout::array::<T>(self.capacity).unwrap();
+
+        if new_cap == 0 {
+            unsafe { self.alloc.deallocate(self.ptr.cast(), old_layout) };
+            self.ptr = NonNull::dangling();
+            self.capacity = 0;
+            return;
+        }
+
+        // Smaller than the existing allocation, so this can't overflow.
+        let new_layout = Layout::array::<T>(new_cap).unwrap();
+        self.ptr = match unsafe { self.alloc.shrink(self.ptr.cast(), old_layout, new_layout) } {
+            Ok(p) => p.cast(),
+            Err(AllocError) => handle_alloc_error(new_layout),
+        };
+        self.capacity = new_cap;
+    }
+}
+
+// Turns a failed reservation into the infallible API's behaviour: panic on
+// overflow, abort on OOM.
+fn handle_reserve(result: Result<(), TryReserveError>) {
+    match result {
+        Ok(()) => {}
+        Err(TryReserveError::CapacityOverflow) => panic!("Allocation too large"),
+        Err(TryReserveError::AllocError { layout }) => handle_alloc_error(layout),
+    }
+}
+
+impl<T> Default for RawVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Send> Send for RawVec<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for RawVec<T, A> {}
+
+impl<T, A: Allocator> Drop for RawVec<T, A> {
+    fn drop(&mut self) {
+        if self.capacity != 0 {
+            let layout = Layout::array::<T>(self.capacity).unwrap();
+            unsafe {
+                self.alloc.deallocate(self.ptr.cast(), layout);
+            }
+        }
+    }
+}
+
+//////////////// GrowthStrategy /////////////////////////
+/////////////////////////////////////////////////////////
+
+/// How far the buffer grows when a push or reserve runs out of room.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrowthStrategy {
+    /// Double the capacity (starting from 1). The default.
+    #[default]
+    Double,
+    /// Grow by half the current capacity, trading more reallocations for
+    /// less slack.
+    OnePointFive,
+    /// Grow by a constant number of elements.
+    Fixed(usize),
+}
+
+//////////////// ShrinkPolicy /////////////////////////
+/////////////////////////////////////////////////////////
+
+/// Whether the buffer gives memory back once a drain leaves it mostly empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShrinkPolicy {
+    /// Keep the capacity at its peak. The default.
+    #[default]
+    Never,
+    /// Shrink to twice the length once fewer than a quarter of the slots
+    /// are in use.
+    Quarter,
+}
+
+//////////////// Allocator /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+/// The allocation interface `RawVec` goes through: a minimal, stable
+/// stand-in for the unstable `core::alloc::Allocator`.
+///
+/// # Safety
+///
+/// A block returned by `allocate`, `grow` or `shrink` must stay valid until
+/// it is handed back to `deallocate`, `grow` or `shrink` on the same
+/// allocator, and must fit the layout it was requested with.
+pub unsafe trait Allocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError>;
+
+    /// # Safety
+    ///
+    /// `ptr` must be a block currently allocated by this allocator with
+    /// `layout`.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+
+    /// Moves the block into one fitting `new_layout`, which must be at least
+    /// as large as `old_layout`. On failure the old block is untouched.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be a block currently allocated by this allocator with
+    /// `old_layout`.
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<u8>, AllocError> {
+        let new_ptr = self.allocate(new_layout)?;
+        copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr(), old_layout.size());
+        self.deallocate(ptr, old_layout);
+        Ok(new_ptr)
+    }
+
+    /// Moves the block into one fitting `new_layout`, which must be no
+    /// larger than `old_layout`. On failure the old block is untouched.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be a block currently allocated by this allocator with
+    /// `old_layout`.
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<u8>, AllocError> {
+        let new_ptr = self.allocate(new_layout)?;
+        copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr(), new_layout.size());
+        self.deallocate(ptr, old_layout);
+        Ok(new_ptr)
+    }
+}
+
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        (**self).deallocate(ptr, layout)
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<u8>, AllocError> {
+        (**self).grow(ptr, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<u8>, AllocError> {
+        (**self).shrink(ptr, old_layout, new_layout)
+    }
+}
+
+/// The allocator could not satisfy a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+impl Error for AllocError {}
+
+/// The global allocator, used unless a `Vector` is built with `new_in`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        // `RawVec` never asks for zero bytes, but `alloc` would be UB if
+        // someone else did.
+        if layout.size() == 0 {
+            return Ok(dangling_for(layout));
+        }
+        NonNull::new(unsafe { alloc(layout) }).ok_or(AllocError)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            dealloc(ptr.as_ptr(), layout);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<u8>, AllocError> {
+        if old_layout.size() == 0 {
+            return self.allocate(new_layout);
+        }
+        NonNull::new(realloc(ptr.as_ptr(), old_layout, new_layout.size())).ok_or(AllocError)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<u8>, AllocError> {
+        if new_layout.size() == 0 {
+            self.deallocate(ptr, old_layout);
+            return Ok(dangling_for(new_layout));
+        }
+        NonNull::new(realloc(ptr.as_ptr(), old_layout, new_layout.size())).ok_or(AllocError)
+    }
+}
+
+// A well-aligned, non-null pointer standing in for a zero-sized block.
+fn dangling_for(layout: Layout) -> NonNull<u8> {
+    unsafe { NonNull::new_unchecked(without_provenance_mut(layout.align())) }
+}
+
+//////////////// TryReserveError /////////////////////////
+/////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TryReserveError {
+    /// The requested capacity exceeds `isize::MAX` bytes.
+    CapacityOverflow,
+    /// The allocator returned null for `layout`.
+    AllocError { layout: Layout },
+}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")?;
+        match self {
+            TryReserveError::CapacityOverflow => {
+                f.write_str(" because the computed capacity exceeded the collection's maximum")
+            }
+            TryReserveError::AllocError { .. } => {
+                f.write_str(" because the memory allocator returned an error")
+            }
+        }
+    }
+}
+
+impl Error for TryReserveError {}
+
+//////////////// IntoIter /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+pub struct IntoIter<T, A: Allocator = Global> {
+    _buf: RawVec<T, A>, // we don't actually care about this. Just need it to live.
+    iter: RawValIter<T>,
+}
+
+// `RawValIter`'s raw pointers opt out of these, but `IntoIter` owns its
+// elements just like the `Vector` it came from.
+unsafe impl<T: Send, A: Allocator + Send> Send for IntoIter<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for IntoIter<T, A> {}
+
+impl<T, A: Allocator> IntoIter<T, A> {
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.iter.as_ptr(), self.len()) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.iter.as_ptr() as *mut T, self.len()) }
+    }
+}
+
+impl<T, A: Allocator> Iterator for IntoIter<T, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.iter.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, A: Allocator> DoubleEndedIterator for IntoIter<T, A> {
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back()
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone> Clone for IntoIter<T, A> {
+    // Only the elements not yet yielded are cloned, into a buffer of their own.
+    fn clone(&self) -> Self {
+        let remaining = self.as_slice();
+        let mut v = Vector::with_capacity_in(remaining.len(), self._buf.alloc.clone());
+        v.extend_from_slice(remaining);
+        v.into_iter()
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator> FusedIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator> Drop for IntoIter<T, A> {
+    fn drop(&mut self) {
+        for _ in &mut *self {}
+    }
+}
+
+//////////////// IntoChunks /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+pub struct IntoChunks<T, A: Allocator = Global> {
+    iter: IntoIter<T, A>,
+    size: usize,
+}
+
+impl<T, A: Allocator> Iterator for IntoChunks<T, A> {
+    type Item = Vector<T>;
+    fn next(&mut self) -> Option<Vector<T>> {
+        let len = min(self.size, self.iter.len());
+        if len == 0 {
+            return None;
+        }
+        let mut chunk = Vector::with_capacity(len);
+        chunk.extend(self.iter.by_ref().take(len));
+        Some(chunk)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let chunks = self.iter.len().div_ceil(self.size);
+        (chunks, Some(chunks))
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for IntoChunks<T, A> {}
+
+impl<T, A: Allocator> FusedIterator for IntoChunks<T, A> {}
+
+//////////////// Drain /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+pub struct Drain<'a, T: 'a, A: Allocator + 'a = Global> {
+    iter: RawValIter<T>,
+    // The vector's `len` sits at the start of the drained range until the
+    // tail `[tail_start, tail_start + tail_len)` is moved back down.
+    vec: NonNull<Vector<T, A>>,
+    tail_start: usize,
+    tail_len: usize,
+    // Length before draining, so the shrink policy only runs when the
+    // drain (or a splice built on it) actually removed elements.
+    orig_len: usize,
+    marker: PhantomData<&'a mut Vector<T, A>>,
+}
+
+// A `Drain` behaves like the `&mut Vector` it borrows.
+unsafe impl<'a, T: Send, A: Allocator + Send> Send for Drain<'a, T, A> {}
+unsafe impl<'a, T: Sync, A: Allocator + Sync> Sync for Drain<'a, T, A> {}
+
+impl<'a, T, A: Allocator> Drain<'a, T, A> {
+    // Stops draining: elements not yet yielded stay in the vector, and
+    // only the gaps left by the yielded ones are closed.
+    pub fn keep_rest(self) {
+        let mut this = ManuallyDrop::new(self);
+        unsafe {
+            let unyielded = this.iter.size_hint().0;
+            let first = this.iter.as_ptr();
+            let vec = this.vec.as_mut();
+            let start = vec.len;
+            copy(first, vec.ptr().add(start), unyielded);
+
+            let kept_end = start + unyielded;
+            copy(
+                vec.ptr().add(this.tail_start),
+                vec.ptr().add(kept_end),
+                this.tail_len,
+            );
+            vec.len = kept_end + this.tail_len;
+        }
+    }
+
+    fn move_tail(&mut self) {
+        unsafe {
+            let vec = self.vec.as_mut();
+            let start = vec.len;
+            if self.tail_len > 0 && self.tail_start != start {
+                copy(
+                    vec.ptr().add(self.tail_start),
+                    vec.ptr().add(start),
+                    self.tail_len,
+                );
+            }
+            vec.len = start + self.tail_len;
+        }
+    }
+
+    // Writes elements from `replace_with` into the hole between the
+    // vector's `len` and `tail_start`. Returns whether the hole was filled.
+    unsafe fn fill<I: Iterator<Item = T>>(&mut self, replace_with: &mut I) -> bool {
+        let vec = self.vec.as_mut();
+        while vec.len < self.tail_start {
+            match replace_with.next() {
+                Some(elem) => {
+                    write(vec.ptr().add(vec.len), elem);
+                    vec.len += 1;
+                }
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // Widens the hole by `additional` slots, shifting the tail up.
+    unsafe fn move_tail_up(&mut self, additional: usize) {
+        let vec = self.vec.as_mut();
+        let used = self.tail_start + self.tail_len;
+        handle_reserve(vec.buf.try_reserve(used, additional));
+
+        let new_tail_start = self.tail_start + additional;
+        copy(
+            vec.ptr().add(self.tail_start),
+            vec.ptr().add(new_tail_start),
+            self.tail_len,
+        );
+        self.tail_start = new_tail_start;
+    }
+}
+
+impl<'a, T, A: Allocator> Iterator for Drain<'a, T, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.iter.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T, A: Allocator> DoubleEndedIterator for Drain<'a, T, A> {
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back()
+    }
+}
+
+impl<'a, T, A: Allocator> ExactSizeIterator for Drain<'a, T, A> {}
+
+impl<'a, T, A: Allocator> FusedIterator for Drain<'a, T, A> {}
+
+impl<'a, T, A: Allocator> Drop for Drain<'a, T, A> {
+    fn drop(&mut self) {
+        // Restores the tail even if one of the remaining elements panics
+        // while being dropped.
+        struct DropGuard<'r, 'a, T, A: Allocator>(&'r mut Drain<'a, T, A>);
+
+        impl<'r, 'a, T, A: Allocator> Drop for DropGuard<'r, 'a, T, A> {
+            fn drop(&mut self) {
+                self.0.move_tail();
+                let vec = unsafe { self.0.vec.as_mut() };
+                if vec.len < self.0.orig_len {
+                    vec.apply_shrink_policy();
+                }
+            }
+        }
+
+        let guard = DropGuard(self);
+        for _ in &mut *guard.0 {}
+    }
+}
+
+//////////////// DrainSorted /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+// The buffer is sorted up front, so this is just a full `Drain`; its drop
+// takes care of any elements that were never yielded.
+pub struct DrainSorted<'a, T: 'a, A: Allocator + 'a = Global> {
+    drain: Drain<'a, T, A>,
+}
+
+impl<'a, T, A: Allocator> Iterator for DrainSorted<'a, T, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.drain.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.drain.size_hint()
+    }
+}
+
+impl<'a, T, A: Allocator> DoubleEndedIterator for DrainSorted<'a, T, A> {
+    fn next_back(&mut self) -> Option<T> {
+        self.drain.next_back()
+    }
+}
+
+impl<'a, T, A: Allocator> ExactSizeIterator for DrainSorted<'a, T, A> {}
+
+impl<'a, T, A: Allocator> FusedIterator for DrainSorted<'a, T, A> {}
+
+//////////////// Splice /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+pub struct Splice<'a, T: 'a, I: Iterator<Item = T>, A: Allocator + 'a = Global> {
+    drain: Drain<'a, T, A>,
+    replace_with: I,
+}
+
+impl<'a, T, I: Iterator<Item = T>, A: Allocator> Iterator for Splice<'a, T, I, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.drain.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.drain.size_hint()
+    }
+}
+
+impl<'a, T, I: Iterator<Item = T>, A: Allocator> DoubleEndedIterator for Splice<'a, T, I, A> {
+    fn next_back(&mut self) -> Option<T> {
+        self.drain.next_back()
+    }
+}
+
+impl<'a, T, I: Iterator<Item = T>, A: Allocator> Drop for Splice<'a, T, I, A> {
+    fn drop(&mut self) {
+        // Finish removing the range first.
+        for _ in &mut self.drain {}
+
+        unsafe {
+            if self.drain.tail_len == 0 {
+                self.drain.vec.as_mut().extend(self.replace_with.by_ref());
+                return;
+            }
+
+            // Fill the hole left by the range.
+            if !self.drain.fill(&mut self.replace_with) {
+                return;
+            }
+
+            // There may be more elements; the lower bound is a cheap guess.
+            let (lower, _) = self.replace_with.size_hint();
+            if lower > 0 {
+                self.drain.move_tail_up(lower);
+                if !self.drain.fill(&mut self.replace_with) {
+                    return;
+                }
+            }
+
+            // Whatever is left has to be buffered to learn how many there are.
+            let mut rest = self
+                .replace_with
+                .by_ref()
+                .collect::<Vector<T>>()
+                .into_iter();
+            let (count, _) = rest.size_hint();
+            if count > 0 {
+                self.drain.move_tail_up(count);
+                let filled = self.drain.fill(&mut rest);
+                debug_assert!(filled);
+            }
+        }
+        // Dropping `drain` moves the tail back against the filled prefix.
+    }
+}
+
+//////////////// ExtractIf /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+pub struct ExtractIf<'a, T, F, A: Allocator = Global>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    vec: &'a mut Vector<T, A>,
+    // Elements `[0, idx - del)` are kept, `[idx - del, idx)` is a hole and
+    // `[idx, old_len)` has not been looked at yet.
+    idx: usize,
+    del: usize,
+    old_len: usize,
+    pred: F,
+    // Set while `pred` runs, so a panicking predicate isn't called again
+    // from `drop`.
+    panic_flag: bool,
+}
+
+impl<'a, T, F, A: Allocator> Iterator for ExtractIf<'a, T, F, A>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        unsafe {
+            while self.idx < self.old_len {
+                let cur = self.vec.ptr().add(self.idx);
+                self.panic_flag = true;
+                let extracted = (self.pred)(&mut *cur);
+                self.panic_flag = false;
+                // Only advance once `pred` has returned, so a panic leaves
+                // the current element in the unexamined part.
+                self.idx += 1;
+                if extracted {
+                    self.del += 1;
+                    return Some(read(cur));
+                } else if self.del > 0 {
+                    copy_nonoverlapping(cur, cur.sub(self.del), 1);
+                }
+            }
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.old_len - self.idx))
+    }
+}
+
+impl<'a, T, F, A: Allocator> Drop for ExtractIf<'a, T, F, A>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    fn drop(&mut self) {
+        // Closes the hole and restores `len`, even if `pred` or a
+        // destructor panics while the rest is being extracted.
+        struct BackshiftOnDrop<'r, 'a, T, F, A>
+        where
+            F: FnMut(&mut T) -> bool,
+            A: Allocator,
+        {
+            extract: &'r mut ExtractIf<'a, T, F, A>,
+        }
+
+        impl<'r, 'a, T, F, A> Drop for BackshiftOnDrop<'r, 'a, T, F, A>
+        where
+            F: FnMut(&mut T) -> bool,
+            A: Allocator,
+        {
+            fn drop(&mut self) {
+                let e = &mut *self.extract;
+                if e.idx < e.old_len && e.del > 0 {
+                    unsafe {
+                        let src = e.vec.ptr().add(e.idx);
+                        copy(src, src.sub(e.del), e.old_len - e.idx);
+                    }
+                }
+                e.vec.len = e.old_len - e.del;
+            }
+        }
+
+        let guard = BackshiftOnDrop { extract: self };
+        if !guard.extract.panic_flag {
+            for _ in &mut *guard.extract {}
+        }
+    }
+}
+
+//////////////// GroupRuns /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+pub struct GroupRuns<'a, T> {
+    rest: &'a [T],
+}
+
+impl<'a, T: PartialEq> Iterator for GroupRuns<'a, T> {
+    type Item = &'a [T];
+    fn next(&mut self) -> Option<&'a [T]> {
+        let first = self.rest.first()?;
+        let len = self.rest.iter().take_while(|elem| *elem == first).count();
+        let (run, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(run)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // At least one run if anything is left, at most one per element.
+        (usize::from(!self.rest.is_empty()), Some(self.rest.len()))
+    }
+}
+
+impl<T: PartialEq> FusedIterator for GroupRuns<'_, T> {}
+
+//////////////// SharedVector /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+// A read-only, reference-counted snapshot of a `Vector`. Clones share the
+// same elements, so it's cheap to hand one to every thread.
+pub struct SharedVector<T> {
+    elems: Arc<[T]>,
+}
+
+impl<T> Clone for SharedVector<T> {
+    fn clone(&self) -> Self {
+        SharedVector {
+            elems: Arc::clone(&self.elems),
+        }
+    }
+}
+
+impl<T> Deref for SharedVector<T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        &self.elems
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SharedVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+//////////////// RawValIter /////////////////////////////////
+/////////////////////////////////////////////////////////
+
+struct RawValIter<T> {
+    start: *const T,
+    end: *const T,
+}
+
+impl<T> RawValIter<T> {
+    // unsafe to construct because it has no associated lifetimes.
+    // This is necessary to store a RawValIter in the same struct as
+    // its actual allocation. OK since it's a private implementation
+    // detail.
+    //
+    // Takes a raw pointer rather than a slice so `start` keeps the buffer's
+    // write provenance; `IntoIter::as_mut_slice` writes through it, which a
+    // pointer derived from a shared `&[T]` wouldn't allow.
+    unsafe fn from_raw_parts(ptr: *const T, len: usize) -> Self {
+        RawValIter {
+            start: ptr,
+            end: if size_of::<T>() == 0 {
+                // ZST pointers can't be offset, so `end` encodes the count
+                // as an address `len` past `start`.
+                (ptr as usize + len) as *const _
+            } else if len == 0 {
+                // if `len = 0`, then this is not actually allocated memory.
+                // Need to avoid offsetting because that will give wrong
+                // information to LLVM via GEP.
+                ptr
+            } else {
+                ptr.add(len)
+            },
+        }
+    }
+
+    // Pointer to the first remaining element. For ZSTs `start` only
+    // encodes a count, so hand out a dangling pointer instead.
+    fn as_ptr(&self) -> *const T {
+        if size_of::<T>() == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            self.start
+        }
+    }
+}
+
+impl<T> DoubleEndedIterator for RawValIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            unsafe {
+                if size_of::<T>() == 0 {
+                    self.end = (self.end as usize - 1) as *const _;
+                    Some(read(NonNull::<T>::dangling().as_ptr()))
+                } else {
+                    self.end = self.end.offset(-1);
+                    Some(read(self.end))
+                }
+            }
+        }
+    }
+}
+
+impl<T> Iterator for RawValIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            unsafe {
+                if size_of::<T>() == 0 {
+                    self.start = (self.start as usize + 1) as *const _;
+                    Some(read(NonNull::<T>::dangling().as_ptr()))
+                } else {
+                    let old_ptr = self.start;
+                    self.start = self.start.offset(1);
+                    Some(read(old_ptr))
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let elem_size = size_of::<T>();
+        let len =
+            (self.end as usize - self.start as usize) / if elem_size == 0 { 1 } else { elem_size };
+        (len, Some(len))
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{
+        cell::Cell,
+        collections::HashSet,
+        panic::{catch_unwind, AssertUnwindSafe},
+        rc::Rc,
+    };
+
+    #[test]
+    fn it_works() {
+        let mut v: Vector<usize> = Vector::new();
+        v.push(16_usize);
+        assert_eq!(v.len(), 1);
+        assert_eq!(v.capacity(), 1);
+        v.push(20_usize);
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.capacity(), 2);
+        v.push(21_usize);
+        assert_eq!(v.len(), 3);
+        assert_eq!(v.capacity(), 4);
+        v.push(23_usize);
+        assert_eq!(v.len(), 4);
+        assert_eq!(v.capacity(), 4);
+        v.push(28_usize);
+        assert_eq!(v.len(), 5);
+        assert_eq!(v.capacity(), 8);
+        v[2] = 20;
+    }
+
+    // Forwards to `Global`, counting calls. `grow` and `shrink` use the
+    // trait's defaults, so every reallocation shows up as an allocate plus a
+    // deallocate.
+    #[derive(Default)]
+    struct CountingAllocator {
+        allocations: Cell<usize>,
+        deallocations: Cell<usize>,
+    }
+
+    unsafe impl Allocator for CountingAllocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+            self.allocations.set(self.allocations.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.deallocations.set(self.deallocations.get() + 1);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    // `n` counters sharing one drop count.
+    fn counted(n: usize) -> (Rc<Cell<usize>>, Vector<DropCounter>) {
+        let drops = Rc::new(Cell::new(0));
+        let v = (0..n).map(|_| DropCounter(drops.clone())).collect();
+        (drops, v)
+    }
+
+    #[test]
+    fn drop_runs_element_destructors() {
+        let drops = Rc::new(Cell::new(0));
+        let mut v = Vector::new();
+        for _ in 0..5 {
+            v.push(DropCounter(drops.clone()));
+        }
+        assert_eq!(drops.get(), 0);
+        drop(v);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn with_capacity_allocates_up_front() {
+        let mut v: Vector<i32> = Vector::with_capacity(10);
+        assert_eq!(v.len(), 0);
+        assert_eq!(v.capacity(), 10);
+
+        let ptr = v.ptr();
+        for i in 0..10 {
+            v.push(i);
+        }
+        assert_eq!(v.capacity(), 10);
+        assert_eq!(v.ptr(), ptr);
+    }
+
+    #[test]
+    fn with_capacity_zero_is_unallocated() {
+        let v: Vector<i32> = Vector::with_capacity(0);
+        assert_eq!(v.capacity(), 0);
+        assert_eq!(v.ptr(), NonNull::dangling().as_ptr());
+    }
+
+    #[test]
+    fn reserve_allocates_once() {
+        let mut v: Vector<i32> = Vector::new();
+        v.reserve(100);
+        assert!(v.capacity() >= 100);
+
+        let (ptr, cap) = (v.ptr(), v.capacity());
+        for i in 0..100 {
+            v.push(i);
+        }
+        assert_eq!(v.ptr(), ptr);
+        assert_eq!(v.capacity(), cap);
+    }
+
+    #[test]
+    fn reserve_keeps_doubling() {
+        let mut v: Vector<i32> = Vector::with_capacity(8);
+        v.push(1);
+        v.reserve(8);
+        assert_eq!(v.capacity(), 16);
+
+        // Already enough room, so nothing changes.
+        v.reserve(2);
+        assert_eq!(v.capacity(), 16);
+    }
+
+    #[test]
+    fn reserve_exact_has_no_slack() {
+        let mut v: Vector<i32> = Vector::new();
+        v.push(1);
+        v.push(2);
+        assert_eq!(v.capacity(), 2);
+
+        v.reserve_exact(3);
+        assert_eq!(v.capacity(), 5);
+
+        // Already enough room, so nothing changes.
+        v.reserve_exact(3);
+        assert_eq!(v.capacity(), 5);
+    }
+
+    #[test]
+    fn try_reserve_reports_capacity_overflow() {
+        let mut v: Vector<i32> = Vector::new();
+        assert_eq!(
+            v.try_reserve(usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+
+        v.push(1);
+        assert_eq!(
+            v.try_reserve(usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        // Too many bytes even though the element count fits in a usize.
+        assert_eq!(
+            v.try_reserve(isize::MAX as usize / 2),
+            Err(TryReserveError::CapacityOverflow)
+        );
+
+        // A failed reservation leaves the vector untouched.
+        assert_eq!(v.capacity(), 1);
+        assert_eq!(&v[..], &[1]);
+        assert_eq!(v.try_reserve(10), Ok(()));
+        assert!(v.capacity() >= 11);
+    }
+
+    #[test]
+    fn shrink_to_fit_releases_slack() {
+        let mut v: Vector<i32> = Vector::new();
+        for i in 0..64 {
+            v.push(i);
+        }
+        for _ in 0..60 {
+            v.pop();
+        }
+        assert_eq!(v.capacity(), 64);
+
+        v.shrink_to_fit();
+        assert_eq!(v.capacity(), 4);
+        assert_eq!(&v[..], &[0, 1, 2, 3]);
+
+        // Already tight, so this is a no-op.
+        v.shrink_to_fit();
+        assert_eq!(v.capacity(), 4);
+    }
+
+    #[test]
+    fn shrink_to_fit_on_empty_deallocates() {
+        let mut v: Vector<i32> = Vector::with_capacity(8);
+        v.shrink_to_fit();
+        assert_eq!(v.capacity(), 0);
+        assert_eq!(v.ptr(), NonNull::dangling().as_ptr());
+
+        // The vector is still usable afterwards.
+        v.push(1);
+        assert_eq!(&v[..], &[1]);
+    }
+
+    #[test]
+    fn shrink_to_keeps_a_floor() {
+        let mut v: Vector<i32> = Vector::with_capacity(16);
+        v.push(1);
+        v.push(2);
+
+        v.shrink_to(100);
+        assert_eq!(v.capacity(), 16);
+
+        v.shrink_to(8);
+        assert_eq!(v.capacity(), 8);
+
+        // Never drops below `len`.
+        v.shrink_to(0);
+        assert_eq!(v.capacity(), 2);
+        assert_eq!(&v[..], &[1, 2]);
+    }
+
+    #[test]
+    fn extend_reserves_from_size_hint() {
+        let mut v: Vector<i32> = Vector::new();
+        v.extend(vec![1, 2, 3]);
+        assert_eq!(&v[..], &[1, 2, 3]);
+        assert_eq!(v.capacity(), 3);
+
+        // One reservation covers the whole range.
+        v.extend(4..=100);
+        assert_eq!(v.len(), 100);
+        assert_eq!(v.capacity(), 100);
+        assert!(v.iter().copied().eq(1..=100));
+    }
+
+    #[test]
+    fn collect_into_vector() {
+        let v: Vector<i32> = (0..10).collect();
+        assert_eq!(v.capacity(), 10);
+        assert!(v.iter().copied().eq(0..10));
+
+        // `filter` has a lower bound of 0, so this falls back to plain pushes.
+        let evens: Vector<i32> = (0..10).filter(|x| x % 2 == 0).collect();
+        assert_eq!(&evens[..], &[0, 2, 4, 6, 8]);
+    }
+
+    #[test]
+    fn debug_formats_like_a_list() {
+        let v: Vector<i32> = (1..=3).collect();
+        assert_eq!(format!("{:?}", v), "[1, 2, 3]");
+        assert_eq!(format!("{:#?}", v), "[\n    1,\n    2,\n    3,\n]");
+        assert_eq!(format!("{:?}", Vector::<i32>::new()), "[]");
+    }
+
+    #[test]
+    fn eq_compares_contents() {
+        let a: Vector<i32> = (1..=3).collect();
+        let b: Vector<i32> = (1..=3).collect();
+        let c: Vector<i32> = (1..=4).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(c, a);
+
+        assert_eq!(a, [1, 2, 3]);
+        assert_ne!(a, [1, 2, 4]);
+        assert_eq!(a, &[1, 2, 3][..]);
+        assert!(a == *[1, 2, 3].as_slice());
+        assert_eq!(Vector::<i32>::new(), []);
+    }
+
+    #[test]
+    fn ord_is_lexicographic() {
+        let empty: Vector<i32> = Vector::new();
+        let a: Vector<i32> = [1, 2].into_iter().collect();
+        let b: Vector<i32> = [1, 2, 3].into_iter().collect();
+        let c: Vector<i32> = [1, 3].into_iter().collect();
+
+        assert!(empty < a);
+        assert!(a < b);
+        assert!(b < c);
+        assert_eq!(a.cmp(&a), Ordering::Equal);
+        assert_eq!(c.cmp(&empty), Ordering::Greater);
+
+        let nan: Vector<f64> = [f64::NAN].into_iter().collect();
+        assert_eq!(nan.partial_cmp(&nan), None);
+    }
+
+    #[test]
+    fn equal_vectors_hash_equally() {
+        let mut set = HashSet::new();
+        set.insert((1..=3).collect::<Vector<i32>>());
+        set.insert((1..=3).collect::<Vector<i32>>());
+        assert_eq!(set.len(), 1);
+
+        set.insert((1..=4).collect::<Vector<i32>>());
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn default_is_empty() {
+        let v: Vector<i32> = Vector::default();
+        assert_eq!(v.len(), 0);
+        assert_eq!(v.capacity(), 0);
+
+        #[derive(Default)]
+        struct Holder {
+            items: Vector<String>,
+        }
+        assert_eq!(Holder::default().items.len(), 0);
+    }
+
+    #[test]
+    fn retain_keeps_matching_in_order() {
+        let mut v: Vector<i32> = (0..10).collect();
+        v.retain(|x| x % 2 == 0);
+        assert_eq!(v, [0, 2, 4, 6, 8]);
+
+        v.retain(|_| false);
+        assert_eq!(v, []);
+    }
+
+    #[test]
+    fn retain_drops_removed_elements() {
+        let (drops, mut v) = counted(10);
+
+        let mut i = 0;
+        v.retain(|_| {
+            i += 1;
+            i % 3 == 0
+        });
+        assert_eq!(v.len(), 3);
+        assert_eq!(drops.get(), 7);
+
+        drop(v);
+        assert_eq!(drops.get(), 10);
+    }
+
+    #[test]
+    fn retain_survives_a_panicking_predicate() {
+        let (drops, mut v) = counted(10);
+
+        let mut i = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            v.retain(|_| {
+                i += 1;
+                if i == 6 {
+                    panic!("predicate failed");
+                }
+                i % 2 == 0
+            })
+        }));
+        assert!(result.is_err());
+
+        // Three of the first five were removed; the panicking element and
+        // everything after it were kept.
+        assert_eq!(drops.get(), 3);
+        assert_eq!(v.len(), 7);
+
+        drop(v);
+        assert_eq!(drops.get(), 10);
+    }
+
+    #[test]
+    fn dedup_removes_consecutive_duplicates() {
+        let mut v: Vector<i32> = [1, 1, 2, 3, 3, 3, 1].into_iter().collect();
+        v.dedup();
+        assert_eq!(v, [1, 2, 3, 1]);
+
+        let mut same: Vector<i32> = [7; 5].into_iter().collect();
+        same.dedup();
+        assert_eq!(same, [7]);
+
+        let mut empty: Vector<i32> = Vector::new();
+        empty.dedup();
+        assert_eq!(empty, []);
+    }
+
+    #[test]
+    fn dedup_by_key_and_predicate() {
+        let mut v: Vector<i32> = [1, -1, 2, -2, -2, 3, 1].into_iter().collect();
+        v.dedup_by_key(|x| x.abs());
+        assert_eq!(v, [1, 2, 3, 1]);
+
+        let mut words: Vector<String> = ["apple", "avocado", "banana", "blueberry", "cherry"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        words.dedup_by(|a, b| a.chars().next() == b.chars().next());
+        assert_eq!(words, ["apple", "banana", "cherry"]);
+    }
+
+    #[test]
+    fn dedup_by_drops_each_duplicate_once() {
+        let (drops, mut v) = counted(6);
+
+        let mut i = 0;
+        // Keeps the first element and every third one after it.
+        v.dedup_by(|_, _| {
+            i += 1;
+            i % 3 != 0
+        });
+        assert_eq!(v.len(), 2);
+        assert_eq!(drops.get(), 4);
+
+        drop(v);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn append_moves_everything() {
+        let mut a: Vector<i32> = (1..=3).collect();
+        let mut b: Vector<i32> = (4..=5).collect();
+        a.append(&mut b);
+
+        assert_eq!(a, [1, 2, 3, 4, 5]);
+        assert_eq!(b, []);
+        assert_eq!(b.capacity(), 2);
+    }
+
+    #[test]
+    fn resize_grows_and_shrinks() {
+        let mut v: Vector<i32> = Vector::new();
+        v.push(1);
+        v.resize(4, 0);
+        assert_eq!(v, [1, 0, 0, 0]);
+        assert_eq!(v.capacity(), 4);
+
+        v.resize(2, 9);
+        assert_eq!(v, [1, 0]);
+    }
+
+    #[test]
+    fn truncate_drops_the_tail() {
+        let (drops, mut v) = counted(5);
+
+        v.truncate(10);
+        assert_eq!(drops.get(), 0);
+
+        v.truncate(2);
+        assert_eq!(v.len(), 2);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn resize_with_calls_the_closure_per_slot() {
+        let mut v: Vector<i32> = Vector::new();
+        let mut next = 0;
+        v.resize_with(3, || {
+            next += 1;
+            next - 1
+        });
+        assert_eq!(v, [0, 1, 2]);
+
+        v.resize_with(1, || unreachable!());
+        assert_eq!(v, [0]);
+    }
+
+    #[test]
+    fn extend_from_slice_clones_onto_the_end() {
+        let mut v: Vector<String> = ["1", "2", "3"].into_iter().map(String::from).collect();
+        let tail = ["4".to_string(), "5".to_string(), "6".to_string()];
+        v.extend_from_slice(&tail);
+        assert_eq!(v, ["1", "2", "3", "4", "5", "6"]);
+        assert_eq!(v.capacity(), 6);
+    }
+
+    #[test]
+    fn is_empty_tracks_len() {
+        let mut v: Vector<i32> = Vector::new();
+        assert!(v.is_empty());
+        v.push(1);
+        assert!(!v.is_empty());
+        v.pop();
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn drain_range_closes_the_gap() {
+        let mut v: Vector<i32> = (0..5).collect();
+        let drained: Vector<i32> = v.drain(1..3).collect();
+        assert_eq!(drained, [1, 2]);
+        assert_eq!(v, [0, 3, 4]);
+
+        let all: Vector<i32> = v.drain(..).collect();
+        assert_eq!(all, [0, 3, 4]);
+        assert!(v.is_empty());
+        assert_eq!(v.capacity(), 5);
+    }
+
+    #[test]
+    fn drain_partially_consumed() {
+        let (drops, mut v) = counted(6);
+
+        let mut drain = v.drain(1..=4);
+        assert_eq!(drain.size_hint(), (4, Some(4)));
+        drop(drain.next());
+        drop(drain.next_back());
+        assert_eq!(drops.get(), 2);
+
+        // The two untaken elements are dropped and the tail moves down.
+        drop(drain);
+        assert_eq!(drops.get(), 4);
+        assert_eq!(v.len(), 2);
+
+        let mut v: Vector<i32> = (0..5).collect();
+        v.drain(2..2);
+        assert_eq!(v, [0, 1, 2, 3, 4]);
+        v.drain(3..);
+        assert_eq!(v, [0, 1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn drain_out_of_bounds_panics() {
+        let mut v: Vector<i32> = (0..3).collect();
+        v.drain(1..4);
+    }
+
+    #[test]
+    fn splice_replaces_a_range() {
+        let mut v: Vector<i32> = (0..4).collect();
+        let removed: Vector<i32> = v.splice(1..3, [9, 9, 9]).collect();
+        assert_eq!(removed, [1, 2]);
+        assert_eq!(v, [0, 9, 9, 9, 3]);
+
+        // Shorter replacement.
+        v.splice(1..4, [7]);
+        assert_eq!(v, [0, 7, 3]);
+
+        // Replacement without a useful size hint.
+        v.splice(1..2, (0..10).filter(|x| x % 3 == 0));
+        assert_eq!(v, [0, 0, 3, 6, 9, 3]);
+
+        // Replacing up to the end just extends.
+        v.splice(4.., [1, 2, 3]);
+        assert_eq!(v, [0, 0, 3, 6, 1, 2, 3]);
+    }
+
+    #[test]
+    fn into_boxed_slice_moves_the_elements() {
+        let mut v: Vector<i32> = Vector::with_capacity(8);
+        v.extend([1, 2, 3]);
+        let boxed = v.into_boxed_slice();
+        assert_eq!(boxed.len(), 3);
+        assert_eq!(&*boxed, &[1, 2, 3]);
+
+        let empty = Vector::<String>::new().into_boxed_slice();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn from_raw_parts_round_trip() {
+        let mut v: Vector<String> = Vector::with_capacity(4);
+        v.push("a".to_string());
+        v.push("b".to_string());
+
+        let (ptr, len, cap) = (v.ptr(), v.len(), v.capacity());
+        forget(v);
+
+        let mut v = unsafe { Vector::from_raw_parts(ptr, len, cap) };
+        assert_eq!(v, ["a", "b"]);
+        assert_eq!(v.capacity(), 4);
+        v.push("c".to_string());
+        assert_eq!(v, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn set_len_exposes_written_elements() {
+        let mut v: Vector<u32> = Vector::new();
+        v.reserve(3);
+        unsafe {
+            for i in 0..3 {
+                write(v.ptr().add(i), i as u32 * 10);
+            }
+            v.set_len(3);
+        }
+        assert_eq!(v, [0, 10, 20]);
+
+        unsafe { v.set_len(1) };
+        assert_eq!(v, [0]);
+    }
+
+    #[test]
+    fn spare_capacity_mut_fills_the_tail() {
+        let mut v: Vector<u32> = Vector::new();
+        v.reserve(4);
+        assert_eq!(v.spare_capacity_mut().len(), 4);
+
+        for (i, slot) in v.spare_capacity_mut().iter_mut().enumerate() {
+            slot.write(i as u32 + 1);
+        }
+        unsafe { v.set_len(4) };
+        assert_eq!(v, [1, 2, 3, 4]);
+        assert!(v.spare_capacity_mut().is_empty());
+    }
+
+    #[test]
+    fn leak_hands_out_the_buffer() {
+        let mut v: Vector<i32> = Vector::with_capacity(8);
+        v.extend([1, 2, 3]);
+        let leaked: &'static mut [i32] = v.leak();
+        leaked[0] = 10;
+        assert_eq!(leaked, &[10, 2, 3]);
+
+        // Reclaim it so leak checkers stay quiet; `leak` shrank it to fit.
+        unsafe { drop(Box::from_raw(leaked as *mut [i32])) };
+    }
+
+    #[test]
+    fn custom_allocator_sees_every_call() {
+        let counter = CountingAllocator::default();
+        let mut v = Vector::new_in(&counter);
+        assert_eq!(counter.allocations.get(), 0);
+
+        // Capacity goes 1, 2, 4, 8: one fresh allocation and three moves.
+        for i in 0..5 {
+            v.push(i);
+        }
+        assert_eq!(v, [0, 1, 2, 3, 4]);
+        assert_eq!(counter.allocations.get(), 4);
+        assert_eq!(counter.deallocations.get(), 3);
+
+        v.shrink_to_fit();
+        assert_eq!(v, [0, 1, 2, 3, 4]);
+        assert_eq!(counter.allocations.get(), 5);
+        assert_eq!(counter.deallocations.get(), 4);
+
+        drop(v);
+        assert_eq!(counter.deallocations.get(), 5);
+
+        // Iterators hold on to the allocator until they are done.
+        let mut v = Vector::with_capacity_in(2, &counter);
+        v.push(String::from("a"));
+        v.push(String::from("b"));
+        let mut iter = v.into_iter();
+        assert_eq!(iter.next().as_deref(), Some("a"));
+        assert_eq!(counter.deallocations.get(), 5);
+        drop(iter);
+        assert_eq!(counter.allocations.get(), 6);
+        assert_eq!(counter.deallocations.get(), 6);
+    }
+
+    fn capacities_while_pushing(mut v: Vector<u8>, pushes: usize) -> Vec<usize> {
+        let mut seen = vec![];
+        for _ in 0..pushes {
+            v.push(0);
+            if seen.last() != Some(&v.capacity()) {
+                seen.push(v.capacity());
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn growth_strategies_differ() {
+        let double = capacities_while_pushing(Vector::new(), 16);
+        assert_eq!(double, [1, 2, 4, 8, 16]);
+        assert_eq!(
+            capacities_while_pushing(Vector::with_growth(GrowthStrategy::Double), 16),
+            double
+        );
+
+        let one_point_five =
+            capacities_while_pushing(Vector::with_growth(GrowthStrategy::OnePointFive), 16);
+        assert_eq!(one_point_five, [1, 2, 3, 4, 6, 9, 13, 19]);
+
+        let fixed = capacities_while_pushing(Vector::with_growth(GrowthStrategy::Fixed(5)), 16);
+        assert_eq!(fixed, [5, 10, 15, 20]);
+    }
+
+    #[test]
+    fn reserve_follows_the_growth_strategy() {
+        let mut v: Vector<u8> = Vector::with_growth(GrowthStrategy::Fixed(10));
+        v.reserve(3);
+        assert_eq!(v.capacity(), 10);
+        v.extend([0; 11]);
+        assert_eq!(v.capacity(), 20);
+
+        // An explicit request larger than one step still wins.
+        v.reserve(50);
+        assert_eq!(v.capacity(), 61);
+    }
+
+    #[test]
+    fn as_slice_views_the_elements() {
+        fn sum(values: &[i32]) -> i32 {
+            values.iter().sum()
+        }
+
+        let mut v: Vector<i32> = (1..=4).collect();
+        assert_eq!(sum(v.as_slice()), 10);
+
+        v.as_mut_slice()[0] = 10;
+        assert_eq!(v.as_slice(), &[10, 2, 3, 4]);
+    }
+
+    #[test]
+    fn as_ptr_points_at_the_first_element() {
+        let mut v: Vector<i32> = (7..10).collect();
+        assert_eq!(unsafe { *v.as_ptr() }, 7);
+
+        unsafe { *v.as_mut_ptr().add(2) = 0 };
+        assert_eq!(v, [7, 8, 0]);
+        assert_eq!(v.as_ptr(), v.as_slice().as_ptr());
+    }
+
+    #[test]
+    fn push_within_capacity_never_grows() {
+        let mut v: Vector<String> = Vector::with_capacity(2);
+        assert_eq!(v.push_within_capacity("a".to_string()), Ok(()));
+        assert_eq!(v.push_within_capacity("b".to_string()), Ok(()));
+        assert_eq!(
+            v.push_within_capacity("c".to_string()),
+            Err("c".to_string())
+        );
+        assert_eq!(v, ["a", "b"]);
+        assert_eq!(v.capacity(), 2);
+    }
+
+    #[test]
+    fn pop_if_checks_the_last_element() {
+        let mut v: Vector<i32> = (1..=3).collect();
+        assert_eq!(v.pop_if(|x| *x % 2 == 1), Some(3));
+        assert_eq!(v.pop_if(|x| *x % 2 == 1), None);
+        assert_eq!(v, [1, 2]);
+
+        // The predicate may edit the element it leaves in place.
+        assert_eq!(
+            v.pop_if(|x| {
+                *x *= 10;
+                false
+            }),
+            None
+        );
+        assert_eq!(v, [1, 20]);
+
+        let mut empty: Vector<i32> = Vector::new();
+        assert_eq!(empty.pop_if(|_| true), None);
+    }
+
+    #[test]
+    fn insert_from_slice_shifts_once() {
+        let mut v: Vector<i32> = (1..=3).collect();
+        v.insert_from_slice(1, &[9, 9]);
+        assert_eq!(v, [1, 9, 9, 2, 3]);
+
+        v.insert_from_slice(5, &[4]);
+        v.insert_from_slice(0, &[0]);
+        v.insert_from_slice(3, &[]);
+        assert_eq!(v, [0, 1, 9, 9, 2, 3, 4]);
+    }
+
+    #[test]
+    fn insert_from_slice_survives_a_panicking_clone() {
+        struct Fragile(Rc<Cell<usize>>, bool);
+
+        impl Clone for Fragile {
+            fn clone(&self) -> Self {
+                assert!(!self.1, "clone failed");
+                Fragile(self.0.clone(), false)
+            }
+        }
+
+        impl Drop for Fragile {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let mut v: Vector<Fragile> = (0..3).map(|_| Fragile(drops.clone(), false)).collect();
+        let source = [Fragile(drops.clone(), false), Fragile(drops.clone(), true)];
+
+        let result = catch_unwind(AssertUnwindSafe(|| v.insert_from_slice(1, &source)));
+        assert!(result.is_err());
+        // The first clone made it in; the tail closed up behind it.
+        assert_eq!(v.len(), 4);
+
+        drop(v);
+        drop(source);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn remove_range_drops_in_place() {
+        let mut v: Vector<i32> = (0..5).collect();
+        v.remove_range(1..3);
+        assert_eq!(v, [0, 3, 4]);
+        v.remove_range(1..);
+        assert_eq!(v, [0]);
+        v.remove_range(..0);
+        assert_eq!(v, [0]);
+
+        let (drops, mut v) = counted(5);
+        v.remove_range(..=2);
+        assert_eq!(drops.get(), 3);
+        assert_eq!(v.len(), 2);
+    }
+
+    #[test]
+    fn remove_range_survives_a_panicking_drop() {
+        struct PanicOnDrop(bool, Rc<Cell<usize>>);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+                assert!(!self.0, "drop failed");
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let mut v: Vector<PanicOnDrop> =
+            (0..5).map(|i| PanicOnDrop(i == 1, drops.clone())).collect();
+
+        let result = catch_unwind(AssertUnwindSafe(|| v.remove_range(1..3)));
+        assert!(result.is_err());
+        // Both range elements were dropped once and the tail moved down.
+        assert_eq!(drops.get(), 2);
+        assert_eq!(v.len(), 3);
+
+        drop(v);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn iterate_by_reference() {
+        let mut v: Vector<i32> = (1..=3).collect();
+
+        let mut sum = 0;
+        for x in &v {
+            sum += *x;
+        }
+        assert_eq!(sum, 6);
+
+        for x in &mut v {
+            *x *= 2;
+        }
+        assert_eq!(v, [2, 4, 6]);
+    }
+
+    #[test]
+    fn as_ref_and_borrow_expose_the_slice() {
+        fn checksum(data: impl AsRef<[u8]>) -> u32 {
+            data.as_ref().iter().map(|&b| b as u32).sum()
+        }
+
+        let mut v: Vector<u8> = (1..=4).collect();
+        assert_eq!(checksum(&v), 10);
+
+        v.as_mut()[0] = 5;
+        assert_eq!(checksum(v.as_ref()), 14);
+
+        let mut set = HashSet::new();
+        set.insert(v);
+        // `Borrow<[T]>` lets a set of vectors be queried with a slice.
+        assert!(set.contains(&[5u8, 2, 3, 4][..]));
+
+        let mut w: Vector<u8> = (1..=2).collect();
+        BorrowMut::<[u8]>::borrow_mut(&mut w)[1] = 9;
+        assert_eq!(w, [1, 9]);
+    }
+
+    #[test]
+    fn from_array_and_slice() {
+        let v = Vector::from([1, 2, 3]);
+        assert_eq!(v, [1, 2, 3]);
+        assert_eq!(v.capacity(), 3);
+
+        let v = Vector::from(&[1, 2, 3][..]);
+        assert_eq!(v, [1, 2, 3]);
+
+        let empty: Vector<i32> = Vector::from([]);
+        assert!(empty.is_empty());
+
+        // Moving out of the array drops each element exactly once.
+        let drops = Rc::new(Cell::new(0));
+        let v = Vector::from([DropCounter(drops.clone()), DropCounter(drops.clone())]);
+        assert_eq!(drops.get(), 0);
+        drop(v);
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn vec_round_trip_is_zero_copy() {
+        let mut vec = Vec::with_capacity(10);
+        vec.extend(["a".to_string(), "b".to_string()]);
+        let ptr = vec.as_ptr();
+
+        let mut v = Vector::from(vec);
+        assert_eq!(v, ["a", "b"]);
+        assert_eq!(v.capacity(), 10);
+        assert_eq!(v.as_ptr(), ptr);
+        v.push("c".to_string());
+
+        let vec: Vec<String> = v.into();
+        assert_eq!(vec, ["a", "b", "c"]);
+        assert_eq!(vec.as_ptr(), ptr);
+
+        let empty = Vector::from(Vec::<i32>::new());
+        assert_eq!(empty.capacity(), 0);
+        assert!(Vec::from(empty).is_empty());
+    }
+
+    #[test]
+    fn vector_macro_forms() {
+        let empty: Vector<i32> = vector![];
+        assert!(empty.is_empty());
+
+        let listed = vector![1, 2, 3];
+        assert_eq!(listed, [1, 2, 3]);
+        assert_eq!(vector![1, 2, 3,], listed);
+
+        let repeated = vector![String::from("x"); 3];
+        assert_eq!(repeated, ["x", "x", "x"]);
+        assert_eq!(repeated.capacity(), 3);
+
+        let none: Vector<String> = vector![String::from("x"); 0];
+        assert!(none.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_appends_bytes() {
+        use std::io::Write;
+
+        let mut buf: Vector<u8> = Vector::new();
+        let world = "world";
+        buf.write_all(b"hello").unwrap();
+        write!(buf, " {world}").unwrap();
+        buf.flush().unwrap();
+        assert_eq!(buf, *b"hello world");
+    }
+
+    #[test]
+    fn retain_mut_edits_kept_elements() {
+        let mut v: Vector<i32> = (1..=6).collect();
+        v.retain_mut(|x| {
+            if *x % 2 == 0 {
+                *x *= 2;
+                true
+            } else {
+                false
+            }
+        });
+        assert_eq!(v, [4, 8, 12]);
+    }
+
+    #[test]
+    fn extract_if_removes_matches() {
+        let mut v: Vector<i32> = (0..10).collect();
+        let evens: Vector<i32> = v.extract_if(|x| *x % 2 == 0).collect();
+        assert_eq!(evens, [0, 2, 4, 6, 8]);
+        assert_eq!(v, [1, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn extract_if_finishes_when_dropped_early() {
+        let mut v: Vector<i32> = (0..10).collect();
+        let mut iter = v.extract_if(|x| *x % 2 == 0);
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next(), Some(2));
+        drop(iter);
+        assert_eq!(v, [1, 3, 5, 7, 9]);
+
+        let (drops, mut v) = counted(6);
+        let mut i = 0;
+        drop(v.extract_if(|_| {
+            i += 1;
+            i > 4
+        }));
+        assert_eq!(drops.get(), 2);
+        assert_eq!(v.len(), 4);
+    }
+
+    #[test]
+    fn extract_if_survives_a_panicking_filter() {
+        let mut v: Vector<i32> = (0..6).collect();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            v.extract_if(|x| {
+                assert!(*x != 3, "filter failed");
+                *x % 2 == 0
+            })
+            .for_each(drop)
+        }));
+        assert!(result.is_err());
+        // 0 and 2 were extracted; the panicking element and the rest stay.
+        assert_eq!(v, [1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn fill_overwrites_every_element() {
+        let mut v = vector![1, 2, 3];
+        v.fill(0);
+        assert_eq!(v, [0, 0, 0]);
+
+        let mut next = 0;
+        v.fill_with(|| {
+            next += 5;
+            next
+        });
+        assert_eq!(v, [5, 10, 15]);
+
+        // Old values are dropped as they are replaced.
+        let (drops, mut v) = counted(3);
+        let other = Rc::new(Cell::new(0));
+        v.fill_with(|| DropCounter(other.clone()));
+        assert_eq!(drops.get(), 3);
+        assert_eq!(other.get(), 0);
+    }
+
+    #[test]
+    fn extend_reallocation_count() {
+        let counter = CountingAllocator::default();
+
+        // An exact size hint is a single allocation.
+        let mut v = Vector::new_in(&counter);
+        v.extend(0..10_000);
+        assert_eq!(v.len(), 10_000);
+        assert_eq!(counter.allocations.get(), 1);
+        drop(v);
+
+        // A hint that says nothing still only doubles: 1, 2, 4, ... 16384.
+        let mut v = Vector::new_in(&counter);
+        v.extend((0..10_000).filter(|_| true));
+        assert_eq!(v.len(), 10_000);
+        assert_eq!(counter.allocations.get(), 1 + 15);
+        drop(v);
+
+        // A short hint is re-read whenever the buffer fills up. `flat_map`
+        // only knows about the chunk it is in, but that is enough to jump
+        // to 2000 straight away: 2000, 4000, 8000, 16000.
+        let mut v = Vector::new_in(&counter);
+        v.extend((0..5).flat_map(|_| 0..2_000));
+        assert_eq!(v.len(), 10_000);
+        assert_eq!(counter.allocations.get() - 16, 4);
+    }
+
+    #[test]
+    fn growth_overflow_is_detected() {
+        // A 1-byte element type with a capacity whose double wraps `usize`.
+        let mut buf: RawVec<u8> = RawVec::new();
+        buf.capacity = usize::MAX / 2 + 1;
+        assert_eq!(
+            buf.try_grow_amortized(buf.capacity, 1),
+            Err(TryReserveError::CapacityOverflow)
+        );
+
+        buf.growth = GrowthStrategy::OnePointFive;
+        assert_eq!(
+            buf.try_grow_amortized(buf.capacity, 1),
+            Err(TryReserveError::CapacityOverflow)
+        );
+
+        buf.growth = GrowthStrategy::Fixed(usize::MAX);
+        assert_eq!(
+            buf.try_grow_amortized(0, 1),
+            Err(TryReserveError::CapacityOverflow)
+        );
+
+        // Nothing was allocated, and the fake capacity must not be freed.
+        assert_eq!(buf.ptr, NonNull::dangling());
+        forget(buf);
+    }
+
+    #[test]
+    #[should_panic(expected = "Allocation too large")]
+    fn growth_overflow_panics_on_push() {
+        let mut v: Vector<u8> = Vector::new();
+        v.buf.capacity = usize::MAX / 2 + 1;
+        v.len = v.buf.capacity;
+        // Unwinding out of here would try to drop 2^63 fake elements.
+        let mut v = ManuallyDrop::new(v);
+        v.push(0);
+    }
+
+    #[test]
+    fn iterators_cross_threads() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let mut v: Vector<i32> = (0..10).collect();
+        assert_send_sync(&v.drain(..2));
+        let drained: Vec<i32> =
+            std::thread::scope(|s| s.spawn(|| v.drain(..3).collect()).join().unwrap());
+        assert_eq!(drained, [2, 3, 4]);
+
+        let iter = v.into_iter();
+        assert_send_sync(&iter);
+        let rest: Vec<i32> = std::thread::spawn(move || iter.collect()).join().unwrap();
+        assert_eq!(rest, [5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn exact_size_iterators() {
+        let v = vector![1, 2, 3];
+        let mut iter = v.into_iter();
+        assert_eq!(iter.len(), 3);
+        iter.next();
+        assert_eq!(iter.len(), 2);
+        iter.next_back();
+        assert_eq!(iter.len(), 1);
+        iter.next();
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+
+        let mut v = vector![1, 2, 3, 4, 5];
+        let mut drain = v.drain(1..4);
+        assert_eq!(drain.len(), 3);
+        drain.next();
+        assert_eq!(drain.len(), 2);
+    }
+
+    #[test]
+    fn into_iter_as_slice() {
+        let mut iter = vector![1, 2, 3, 4].into_iter();
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.as_slice(), [2, 3, 4]);
+        iter.as_mut_slice()[0] = 20;
+        assert_eq!(iter.next_back(), Some(4));
+        assert_eq!(iter.as_slice(), [20, 3]);
+        assert_eq!(iter.next(), Some(20));
+        iter.next();
+        assert!(iter.as_slice().is_empty());
+    }
+
+    #[test]
+    fn iter_and_iter_mut() {
+        let mut v = vector![1, 2, 3];
+        for elem in v.iter_mut() {
+            *elem *= 10;
+        }
+        assert_eq!(v, [10, 20, 30]);
+        assert_eq!(v.iter().sum::<i32>(), 60);
+        assert_eq!(Vector::<i32>::new().iter_mut().next(), None);
+    }
+
+    #[test]
+    fn first_and_last() {
+        let mut v: Vector<i32> = Vector::new();
+        assert_eq!(v.first(), None);
+        assert_eq!(v.last(), None);
+        assert_eq!(v.first_mut(), None);
+        assert_eq!(v.last_mut(), None);
+
+        v.push(1);
+        assert_eq!(v.first(), Some(&1));
+        assert_eq!(v.last(), Some(&1));
+
+        v.extend([2, 3]);
+        *v.first_mut().unwrap() = 10;
+        *v.last_mut().unwrap() = 30;
+        assert_eq!(v, [10, 2, 30]);
+        assert_eq!(v.first(), Some(&10));
+        assert_eq!(v.last(), Some(&30));
+    }
+
+    #[test]
+    fn get_and_get_mut() {
+        let mut v = vector![1, 2, 3];
+        assert_eq!(v.get(0), Some(&1));
+        assert_eq!(v.get(2), Some(&3));
+        assert_eq!(v.get(3), None);
+        assert_eq!(v.get(usize::MAX), None);
+
+        *v.get_mut(1).unwrap() = 20;
+        assert_eq!(v.get_mut(3), None);
+        assert_eq!(v, [1, 20, 3]);
+    }
+
+    #[test]
+    fn swap_elements() {
+        let mut v = vector![1, 2, 3];
+        v.swap(0, 2);
+        assert_eq!(v, [3, 2, 1]);
+        v.swap(1, 1);
+        assert_eq!(v, [3, 2, 1]);
+
+        let result = catch_unwind(AssertUnwindSafe(|| v.swap(0, 3)));
+        assert!(result.is_err());
+        assert_eq!(v, [3, 2, 1]);
+    }
+
+    #[test]
+    fn resize_default() {
+        let mut v: Vector<i32> = Vector::new();
+        v.resize_default(3);
+        assert_eq!(v, [0, 0, 0]);
+        v[1] = 5;
+        v.resize_default(2);
+        assert_eq!(v, [0, 5]);
+    }
+
+    #[test]
+    fn from_elem() {
+        let v = Vector::from_elem(7, 3);
+        assert_eq!(v, [7, 7, 7]);
+        assert_eq!(v.capacity(), 3);
+        assert!(Vector::from_elem(7, 0).is_empty());
+
+        // `elem` itself ends up in the vector, and every slot is dropped with it.
+        let count = Rc::new(Cell::new(0));
+        let v = Vector::from_elem(Rc::clone(&count), 4);
+        assert_eq!(Rc::strong_count(&count), 5);
+        drop(v);
+        assert_eq!(Rc::strong_count(&count), 1);
+    }
+
+    #[test]
+    fn extend_from_copy_slice_allocates_once() {
+        let bytes: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let counter = CountingAllocator::default();
+        let mut v = Vector::new_in(&counter);
+        v.extend_from_copy_slice(&bytes);
+        assert_eq!(v, bytes[..]);
+        assert_eq!(counter.allocations.get(), 1);
+
+        v.extend_from_copy_slice(&[]);
+        let mut w = Vector::new();
+        w.extend_from_copy_slice(&v[..2]);
+        w.extend_from_copy_slice(&v[998..]);
+        assert_eq!(w, [0, 1, 230, 231]);
+        assert_eq!(counter.allocations.get(), 1);
+    }
+
+    #[test]
+    fn insert_at_len_is_push() {
+        let mut pushed = Vector::new();
+        let mut inserted = Vector::new();
+        for i in 0..20 {
+            pushed.push(i);
+            inserted.insert(inserted.len(), i);
+            assert_eq!(inserted, pushed);
+            assert_eq!(inserted.capacity(), pushed.capacity());
+        }
+    }
+
+    #[test]
+    fn insert_into_full_vector_grows() {
+        let mut v = Vector::with_capacity(3);
+        v.extend([1, 2, 3]);
+        assert_eq!(v.len(), v.capacity());
+        v.insert(1, 9);
+        assert_eq!(v, [1, 9, 2, 3]);
+        assert!(v.capacity() >= 4);
+    }
+
+    // Small xorshift generator so randomized tests are reproducible
+    // without pulling in a dependency.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        // Uniform enough in `0..n` for test purposes.
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    #[test]
+    fn insert_at_random_indices_matches_vec() {
+        for seed in 1..=20 {
+            let mut rng = Rng(seed);
+            let mut v = Vector::new();
+            let mut expected = Vec::new();
+            for i in 0..200 {
+                let index = rng.below(expected.len() + 1);
+                v.insert(index, i);
+                expected.insert(index, i);
+                assert_eq!(v, expected[..]);
+            }
+        }
+    }
+
+    // Operations for the parity test. Indices are taken modulo the current
+    // length when applied, so any prefix of a sequence is still valid.
+    #[derive(Clone, Debug)]
+    enum Op {
+        Push(i32),
+        Pop,
+        Insert(usize, i32),
+        Remove(usize),
+        Drain(usize, usize),
+    }
+
+    impl Op {
+        fn random(rng: &mut Rng) -> Op {
+            let value = rng.next() as i32;
+            let (a, b) = (rng.below(64), rng.below(64));
+            match rng.below(5) {
+                0 => Op::Push(value),
+                1 => Op::Pop,
+                2 => Op::Insert(a, value),
+                3 => Op::Remove(a),
+                _ => Op::Drain(a, b),
+            }
+        }
+
+        fn apply(&self, v: &mut Vector<i32>, expected: &mut Vec<i32>) {
+            let len = expected.len();
+            match *self {
+                Op::Push(x) => {
+                    v.push(x);
+                    expected.push(x);
+                }
+                Op::Pop => assert_eq!(v.pop(), expected.pop()),
+                Op::Insert(i, x) => {
+                    let i = i % (len + 1);
+                    v.insert(i, x);
+                    expected.insert(i, x);
+                }
+                Op::Remove(_) if len == 0 => {}
+                Op::Remove(i) => assert_eq!(v.remove(i % len), expected.remove(i % len)),
+                Op::Drain(a, b) => {
+                    let (a, b) = (a % (len + 1), b % (len + 1));
+                    let range = a.min(b)..a.max(b);
+                    assert!(v.drain(range.clone()).eq(expected.drain(range)));
+                }
+            }
+        }
+    }
 
     #[test]
-    fn it_works() {
-        let mut v: Vector<usize> = Vector::new();
-        v.push(16 as usize);
-        assert_eq!(v.len(), 1);
-        assert_eq!(v.capacity(), 1);
-        v.push(20 as usize);
-        assert_eq!(v.len(), 2);
-        assert_eq!(v.capacity(), 2);
-        v.push(21 as usize);
-        assert_eq!(v.len(), 3);
-        assert_eq!(v.capacity(), 4);
-        v.push(23 as usize);
-        assert_eq!(v.len(), 4);
-        assert_eq!(v.capacity(), 4);
-        v.push(28 as usize);
-        assert_eq!(v.len(), 5);
-        assert_eq!(v.capacity(), 8);
-        v[2] = 20;
+    fn random_operations_match_vec() {
+        for seed in 1..=200 {
+            let mut rng = Rng(seed);
+            let ops: Vec<Op> = (0..100).map(|_| Op::random(&mut rng)).collect();
+
+            let mut v = Vector::new();
+            let mut expected = Vec::new();
+            for (step, op) in ops.iter().enumerate() {
+                op.apply(&mut v, &mut expected);
+                // The failing prefix is the reproduction case.
+                assert!(
+                    v == expected[..] && v.len() == expected.len(),
+                    "seed {seed} diverged after {:?}",
+                    &ops[..=step]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let v = vector![1, 2, 3];
+        assert!(v.contains(&2));
+        assert!(!v.contains(&9));
+        assert!(!Vector::<i32>::new().contains(&0));
+    }
+
+    #[test]
+    fn insert_sorted() {
+        let mut v = vector![1, 3, 5];
+        assert_eq!(v.insert_sorted(0), 0);
+        assert_eq!(v.insert_sorted(4), 3);
+        assert_eq!(v.insert_sorted(6), 5);
+        assert_eq!(v, [0, 1, 3, 4, 5, 6]);
+
+        // Duplicates go after the existing run.
+        let mut v = vector![Key(1, 'a'), Key(2, 'a'), Key(2, 'b'), Key(3, 'a')];
+        assert_eq!(v.insert_sorted(Key(2, 'c')), 3);
+        let tags: Vec<char> = v.iter().map(|key| key.1).collect();
+        assert_eq!(tags, ['a', 'a', 'b', 'c', 'a']);
+
+        // Orders on the number only, so `char` shows where ties land.
+        #[derive(Debug)]
+        struct Key(i32, char);
+        impl PartialEq for Key {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl Eq for Key {}
+        impl PartialOrd for Key {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Key {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+    }
+
+    #[test]
+    fn split_at_owned() {
+        let (front, back) = vector![1, 2, 3, 4].split_at_owned(2);
+        assert_eq!(front, [1, 2]);
+        assert_eq!(back, [3, 4]);
+
+        let (front, back) = vector![1, 2].split_at_owned(2);
+        assert_eq!(front, [1, 2]);
+        assert!(back.is_empty());
+
+        // Every element is dropped exactly once across the two halves.
+        let (drops, v) = counted(5);
+        let (front, back) = v.split_at_owned(0);
+        assert_eq!((front.len(), back.len()), (0, 5));
+        drop((front, back));
+        assert_eq!(drops.get(), 5);
+
+        let result = catch_unwind(|| vector![1].split_at_owned(2));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rotate_in_place() {
+        let counter = CountingAllocator::default();
+        let mut v = Vector::new_in(&counter);
+        v.extend([1, 2, 3, 4, 5]);
+        let allocations = counter.allocations.get();
+
+        v.rotate_left(2);
+        assert_eq!(v, [3, 4, 5, 1, 2]);
+        v.rotate_right(2);
+        assert_eq!(v, [1, 2, 3, 4, 5]);
+        v.rotate_left(5);
+        assert_eq!(v, [1, 2, 3, 4, 5]);
+        assert_eq!(counter.allocations.get(), allocations);
+    }
+
+    #[test]
+    fn reverse() {
+        let mut v = vector![1, 2, 3];
+        v.reverse();
+        assert_eq!(v, [3, 2, 1]);
+
+        let mut v: Vector<i32> = Vector::new();
+        v.reverse();
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn sorting() {
+        let mut v = vector![3, 1, 2];
+        v.sort();
+        assert_eq!(v, [1, 2, 3]);
+
+        let mut v = vector![3, 1, 2];
+        v.sort_unstable();
+        assert_eq!(v, [1, 2, 3]);
+
+        v.sort_by(|a, b| b.cmp(a));
+        assert_eq!(v, [3, 2, 1]);
+
+        let mut v = vector![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')];
+        v.sort_by_key(|&(k, _)| k);
+        assert_eq!(v, [(1, 'b'), (1, 'd'), (2, 'a'), (2, 'c')]);
+    }
+
+    #[test]
+    fn sort_by_survives_a_panicking_comparator() {
+        let drops = Rc::new(Cell::new(0));
+        let mut v: Vector<(usize, DropCounter)> = (0..20)
+            .map(|i| ((i * 7) % 20, DropCounter(drops.clone())))
+            .collect();
+
+        let mut calls = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            v.sort_by(|a, b| {
+                calls += 1;
+                assert!(calls < 30, "comparator failed");
+                a.0.cmp(&b.0)
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 0);
+
+        // Still a permutation of the original keys: nothing duplicated or lost.
+        let mut keys: Vec<usize> = v.iter().map(|(k, _)| *k).collect();
+        keys.sort();
+        assert_eq!(keys, (0..20).collect::<Vec<_>>());
+
+        drop(v);
+        assert_eq!(drops.get(), 20);
+    }
+
+    #[test]
+    fn concat() {
+        let nested = vector![vector![1, 2], vector![3], Vector::new(), vector![4, 5]];
+        let flat = nested.concat();
+        assert_eq!(flat, [1, 2, 3, 4, 5]);
+        assert_eq!(flat.capacity(), 5);
+        assert_eq!(nested.len(), 4);
+
+        let empty: Vector<Vector<i32>> = Vector::new();
+        assert!(empty.concat().is_empty());
+    }
+
+    #[test]
+    fn partition_dedup() {
+        let mut v = vector![1, 1, 2, 2, 2, 3];
+        let removed = v.partition_dedup();
+        assert_eq!(v, [1, 2, 3]);
+        assert_eq!(removed, [1, 2, 2]);
+
+        let mut v = vector![(1, 'a'), (1, 'a'), (2, 'b')];
+        assert_eq!(v.partition_dedup(), [(1, 'a')]);
+        assert_eq!(v, [(1, 'a'), (2, 'b')]);
+
+        let mut v = vector![1, 2, 3];
+        assert!(v.partition_dedup().is_empty());
+        assert_eq!(v, [1, 2, 3]);
+    }
+
+    #[test]
+    fn clone_into_iter() {
+        let mut iter = vector![1, 2, 3, 4].into_iter();
+        assert_eq!(iter.next(), Some(1));
+
+        let mut copy = iter.clone();
+        assert_eq!(copy.next_back(), Some(4));
+        assert_eq!(iter.collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(copy.collect::<Vec<_>>(), [2, 3]);
+
+        let shared = Rc::new(());
+        let mut iter = Vector::from_elem(shared.clone(), 3).into_iter();
+        drop(iter.next());
+        let copy = iter.clone();
+        assert_eq!(copy.len(), 2);
+        assert_eq!(Rc::strong_count(&shared), 5);
+        drop((iter, copy));
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn drain_keep_rest() {
+        let mut v: Vector<i32> = (0..6).collect();
+        let mut drain = v.drain(1..4);
+        assert_eq!(drain.next(), Some(1));
+        drain.keep_rest();
+        assert_eq!(v, [0, 2, 3, 4, 5]);
+
+        // Elements taken from the back leave their own gap.
+        let mut v: Vector<i32> = (0..6).collect();
+        let mut drain = v.drain(1..5);
+        assert_eq!(drain.next(), Some(1));
+        assert_eq!(drain.next_back(), Some(4));
+        drain.keep_rest();
+        assert_eq!(v, [0, 2, 3, 5]);
+
+        let mut v: Vector<i32> = (0..4).collect();
+        v.drain(..).keep_rest();
+        assert_eq!(v, [0, 1, 2, 3]);
+
+        let (drops, mut v) = counted(4);
+        let mut drain = v.drain(..3);
+        drop(drain.next());
+        drain.keep_rest();
+        assert_eq!((v.len(), drops.get()), (3, 1));
+        drop(v);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn allocation_introspection() {
+        let mut v: Vector<u32> = Vector::new();
+        assert_eq!(v.allocated_bytes(), 0);
+        assert_eq!(v.current_layout(), None);
+
+        v.push(1);
+        assert_eq!(v.allocated_bytes(), 4);
+        assert_eq!(v.current_layout(), Some(Layout::new::<u32>()));
+
+        v.extend(0..100);
+        let bytes = v.capacity() * 4;
+        assert_eq!(v.allocated_bytes(), bytes);
+        assert_eq!(
+            v.current_layout(),
+            Some(Layout::array::<u32>(v.capacity()).unwrap())
+        );
+
+        v.truncate(0);
+        assert_eq!(v.allocated_bytes(), bytes);
+        v.shrink_to_fit();
+        assert_eq!(v.current_layout(), None);
+    }
+
+    #[test]
+    fn shared_vector_across_threads() {
+        let shared = vector![1, 2, 3, 4].into_shared();
+        let handles: Vec<_> = (0..2)
+            .map(|i| {
+                let shared = shared.clone();
+                std::thread::spawn(move || shared.iter().sum::<i32>() * i)
+            })
+            .collect();
+        let sums: Vec<i32> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(sums, [0, 10]);
+        assert_eq!(&*shared, [1, 2, 3, 4]);
+        assert_eq!(format!("{shared:?}"), "[1, 2, 3, 4]");
+    }
+
+    #[test]
+    fn try_push() {
+        let mut v = Vector::new();
+        assert_eq!(v.try_push(1), Ok(()));
+        assert_eq!(v.try_push(2), Ok(()));
+        assert_eq!(v, [1, 2]);
+
+        let mut v: Vector<u8> = Vector::new();
+        v.buf.capacity = usize::MAX / 2 + 1;
+        v.len = v.buf.capacity;
+        let mut v = ManuallyDrop::new(v);
+        assert_eq!(v.try_push(7), Err((TryReserveError::CapacityOverflow, 7)));
+        assert_eq!(v.len, usize::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn raw_vec_grow_to() {
+        let mut buf: RawVec<u32> = RawVec::new();
+        assert_eq!(buf.grow_to(4), Ok(()));
+        assert_eq!(buf.capacity(), 4);
+        assert_ne!(buf.ptr(), NonNull::dangling().as_ptr());
+
+        unsafe {
+            for i in 0..4 {
+                write(buf.ptr().add(i), i as u32);
+            }
+            assert_eq!(buf.grow_to(10), Ok(()));
+            assert_eq!(buf.capacity(), 10);
+            assert_eq!(slice::from_raw_parts(buf.ptr(), 4), [0, 1, 2, 3]);
+        }
+
+        let ptr = buf.ptr();
+        assert_eq!(
+            buf.grow_to(usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        assert_eq!((buf.ptr(), buf.capacity()), (ptr, 10));
+
+        buf.grow();
+        assert_eq!(buf.capacity(), 20);
+    }
+
+    #[test]
+    fn shrink_policy_after_drain() {
+        let mut v: Vector<i32> = (0..100).collect();
+        let peak = v.capacity();
+        v.drain(..);
+        assert_eq!(v.capacity(), peak);
+
+        let mut v: Vector<i32> = (0..100).collect();
+        v.set_shrink_policy(ShrinkPolicy::Quarter);
+        v.drain(..60);
+        assert_eq!(v.capacity(), peak);
+        v.drain(..15);
+        assert_eq!((v.len(), v.capacity()), (25, peak));
+        v.drain(..1);
+        assert_eq!((v.len(), v.capacity()), (24, 48));
+        assert_eq!(v, (76..100).collect::<Vec<_>>()[..]);
+
+        v.drain(..);
+        assert_eq!(v.capacity(), 0);
+    }
+
+    #[test]
+    fn unique_sorted() {
+        let mut v = vector![3, 1, 2, 3, 1];
+        v.unique_sorted();
+        assert_eq!(v, [1, 2, 3]);
+
+        let mut v: Vector<i32> = Vector::new();
+        v.unique_sorted();
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn into_chunks() {
+        let chunks: Vec<Vector<i32>> = vector![1, 2, 3, 4, 5].into_chunks(2).collect();
+        assert_eq!(chunks, [vector![1, 2], vector![3, 4], vector![5]]);
+
+        let mut chunks = vector![1, 2, 3, 4].into_chunks(2);
+        assert_eq!(chunks.len(), 2);
+        chunks.next();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(Vector::<i32>::new().into_chunks(3).next(), None);
+
+        // Unclaimed elements are dropped with the iterator.
+        let (drops, v) = counted(5);
+        let mut chunks = v.into_chunks(3);
+        drop(chunks.next());
+        assert_eq!(drops.get(), 3);
+        drop(chunks);
+        assert_eq!(drops.get(), 5);
+
+        assert!(catch_unwind(|| vector![1].into_chunks(0)).is_err());
+    }
+
+    #[test]
+    fn windows() {
+        let v = vector![1, 2, 3];
+        let mut windows = v.windows(2);
+        assert_eq!(windows.next(), Some(&[1, 2][..]));
+        assert_eq!(windows.next(), Some(&[2, 3][..]));
+        assert_eq!(windows.next(), None);
+        assert_eq!(v.windows(4).count(), 0);
+    }
+
+    #[test]
+    fn insert_and_remove_check_offsets() {
+        // A fake buffer past the (lowered) offset limit; the checks must
+        // fire before anything is read or written.
+        let mut v: Vector<u8> = Vector::new();
+        v.buf.capacity = MAX_OFFSET_BYTES + 8;
+        v.len = MAX_OFFSET_BYTES + 4;
+        let mut v = ManuallyDrop::new(v);
+
+        let result = catch_unwind(AssertUnwindSafe(|| v.remove(MAX_OFFSET_BYTES)));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert_eq!(
+            message,
+            format!("offset of index {MAX_OFFSET_BYTES} overflows the buffer")
+        );
+        assert_eq!(v.len, MAX_OFFSET_BYTES + 4);
+
+        let len = v.len;
+        let result = catch_unwind(AssertUnwindSafe(|| v.insert(len, 0)));
+        assert!(result.is_err());
+        assert_eq!(v.len, MAX_OFFSET_BYTES + 4);
+
+        assert_offset_in_bounds::<u8>(MAX_OFFSET_BYTES - 1, MAX_OFFSET_BYTES);
+        assert_offset_in_bounds::<u32>(MAX_OFFSET_BYTES / 4 - 1, MAX_OFFSET_BYTES);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extend_from_reader() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut v: Vector<u8> = Vector::new();
+        assert_eq!(v.extend_from_reader(&mut &data[..]).unwrap(), 100);
+        assert_eq!(v, data[..]);
+
+        assert_eq!(v.extend_from_reader(&mut &b"ab"[..]).unwrap(), 2);
+        assert_eq!(v[100..], *b"ab");
+        assert_eq!(v.extend_from_reader(&mut &[][..]).unwrap(), 0);
+        assert_eq!(v.len(), 102);
+    }
+
+    #[test]
+    fn group_runs() {
+        let v = vector![1, 1, 2, 3, 3];
+        let runs: Vec<&[i32]> = v.group_runs().collect();
+        assert_eq!(runs, [&[1, 1][..], &[2], &[3, 3]]);
+        assert_eq!(v, [1, 1, 2, 3, 3]);
+
+        assert_eq!(Vector::<i32>::new().group_runs().next(), None);
+        assert_eq!(vector![7; 4].group_runs().count(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn dedup_total() {
+        let mut v = vector![1, 2, 1, 3, 2];
+        v.dedup_total();
+        assert_eq!(v, [1, 2, 3]);
+
+        let mut v = vector!["b", "a", "b", "b", "c", "a"];
+        v.dedup_total();
+        assert_eq!(v, ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn remove_all() {
+        let mut v = vector![-1, 2, -3, 4];
+        assert_eq!(v.remove_all(|&x| x < 0), 2);
+        assert_eq!(v, [2, 4]);
+        assert_eq!(v.remove_all(|&x| x < 0), 0);
+
+        // A panicking predicate leaves every element accounted for.
+        let (drops, mut v) = counted(6);
+        let mut seen = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            v.remove_all(|_| {
+                seen += 1;
+                assert!(seen < 4, "predicate failed");
+                seen % 2 == 1
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!((v.len(), drops.get()), (4, 2));
+        drop(v);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn map() {
+        let v = vector![1, 2, 3];
+        assert_eq!(v.map(|x| x * 2), [2, 4, 6]);
+
+        let v = vector![1, 2, 3];
+        let strings = v.map(|x| x.to_string());
+        assert_eq!(strings, ["1", "2", "3"]);
+        assert_eq!(strings.capacity(), 3);
+    }
+
+    #[test]
+    fn map_in_place_reuses_the_buffer() {
+        let v: Vector<u32> = vector![1, 2, 3, u32::MAX];
+        let ptr = v.as_ptr() as usize;
+        let mapped: Vector<i32> = v.map_in_place(|x| x as i32);
+        assert_eq!(mapped, [1, 2, 3, -1]);
+        assert_eq!(mapped.as_ptr() as usize, ptr);
+
+        // Different layouts take the `map` path.
+        let v = vector![1u8, 2, 3];
+        assert_eq!(v.map_in_place(u64::from), [1, 2, 3]);
+    }
+
+    #[test]
+    fn map_in_place_survives_a_panicking_closure() {
+        let (drops, v) = counted(5);
+        let mut calls = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            v.map_in_place(|elem| {
+                calls += 1;
+                assert!(calls < 3, "map failed");
+                elem
+            })
+        }));
+        assert!(result.is_err());
+        // Two mapped, one consumed by the panicking call, two untouched.
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn filter_map() {
+        let v = vector!["1", "x", "3"];
+        assert_eq!(v.filter_map(|s| s.parse::<i32>().ok()), [1, 3]);
+
+        // Discarded elements are dropped along the way.
+        let (drops, v) = counted(4);
+        let mut i = 0;
+        let kept = v.filter_map(|elem| {
+            i += 1;
+            (i % 2 == 0).then_some(elem)
+        });
+        assert_eq!((kept.len(), drops.get()), (2, 2));
+    }
+
+    #[test]
+    fn retain_take() {
+        let mut v = vector![1, 2, 3, 4];
+        let removed = v.retain_take(|x| x % 2 == 0);
+        assert_eq!(v, [2, 4]);
+        assert_eq!(removed, [1, 3]);
+
+        assert!(v.retain_take(|_| true).is_empty());
+        assert_eq!(v.retain_take(|_| false), [2, 4]);
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn swap_with_slice() {
+        let mut v = vector![1, 2, 3];
+        let mut other = [7, 8, 9];
+        v.swap_with_slice(&mut other);
+        assert_eq!(v, [7, 8, 9]);
+        assert_eq!(other, [1, 2, 3]);
+
+        let result = catch_unwind(AssertUnwindSafe(|| v.swap_with_slice(&mut [0; 2])));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn as_chunks() {
+        let mut v = vector![1, 2, 3, 4, 5];
+        let (chunks, rest) = v.as_chunks::<2>();
+        assert_eq!(chunks, [[1, 2], [3, 4]]);
+        assert_eq!(rest, [5]);
+
+        let (chunks, rest) = v.as_chunks_mut::<2>();
+        chunks[1].swap(0, 1);
+        rest[0] = 50;
+        assert_eq!(v, [1, 2, 4, 3, 50]);
+        assert_eq!(v.as_chunks::<8>().1.len(), 5);
+    }
+
+    #[test]
+    fn insert_many() {
+        let mut v = vector![9, 9];
+        v.insert_many(1, 0..3);
+        assert_eq!(v, [9, 0, 1, 2, 9]);
+
+        // No usable size hint, and non-`Clone` elements.
+        let mut v = vector![String::from("a"), String::from("d")];
+        v.insert_many(1, ["b", "c"].iter().filter(|_| true).map(|s| s.to_string()));
+        assert_eq!(v, ["a", "b", "c", "d"]);
+
+        v.insert_many(4, Some(String::from("e")));
+        v.insert_many(0, None);
+        assert_eq!(v, ["a", "b", "c", "d", "e"]);
+
+        assert!(catch_unwind(AssertUnwindSafe(|| v.insert_many(6, None))).is_err());
+
+        // Inserting never gives capacity back, whatever the shrink policy.
+        let mut v = vector![1];
+        v.reserve(99);
+        v.set_shrink_policy(ShrinkPolicy::Quarter);
+        let capacity = v.capacity();
+        v.insert_many(0, [0]);
+        v.insert_many(2, None);
+        assert_eq!(v, [0, 1]);
+        assert_eq!(v.capacity(), capacity);
+    }
+
+    #[test]
+    fn drain_sorted() {
+        let mut v = vector![3, 1, 2];
+        assert_eq!(v.drain_sorted().collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(v.is_empty());
+
+        // Elements that were never yielded are dropped with the iterator.
+        let shared = Rc::new(());
+        let mut v: Vector<(i32, Rc<()>)> = [3, 1, 2].map(|k| (k, shared.clone())).into();
+        let mut drain = v.drain_sorted();
+        assert_eq!(drain.next().map(|(k, _)| k), Some(1));
+        assert_eq!(drain.len(), 2);
+        drop(drain);
+        assert_eq!(Rc::strong_count(&shared), 1);
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn binary_insert_by() {
+        let mut v = vector![1, 3, 5];
+        assert_eq!(v.binary_insert_by(0, |x| x.cmp(&0)), 0);
+        assert_eq!(v.binary_insert_by(2, |x| x.cmp(&2)), 2);
+        assert_eq!(v.binary_insert_by(6, |x| x.cmp(&6)), 5);
+        assert_eq!(v, [0, 1, 2, 3, 5, 6]);
+
+        // Sorted by a derived key, ties going last.
+        let mut v = vector!["bb", "a", "ccc"];
+        v.sort_by_key(|s| s.len());
+        assert_eq!(v.binary_insert_by("dd", |s| s.len().cmp(&2)), 2);
+        assert_eq!(v, ["a", "bb", "dd", "ccc"]);
+    }
+
+    #[test]
+    fn alternating_ends_meet_once() {
+        let mut iter = vector![1, 2, 3, 4, 5].into_iter();
+        let mut seen = vec![];
+        for i in 0..5 {
+            let next = if i % 2 == 0 {
+                iter.next()
+            } else {
+                iter.next_back()
+            };
+            seen.push(next.unwrap());
+        }
+        assert_eq!(seen, [1, 5, 2, 4, 3]);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let mut v = vector![0, 1, 2, 3, 4, 5, 6];
+        let mut drain = v.drain(1..6);
+        assert_eq!(drain.next(), Some(1));
+        assert_eq!(drain.next_back(), Some(5));
+        assert_eq!(drain.next(), Some(2));
+        assert_eq!(drain.next_back(), Some(4));
+        assert_eq!(drain.next(), Some(3));
+        assert_eq!((drain.next(), drain.next_back()), (None, None));
+        drop(drain);
+        assert_eq!(v, [0, 6]);
+    }
+
+    #[test]
+    fn raw_val_iter_counts_zsts() {
+        let units = [(); 5];
+        let mut iter = unsafe { RawValIter::from_raw_parts(units.as_ptr(), units.len()) };
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!(iter.next(), Some(()));
+        assert_eq!(iter.next_back(), Some(()));
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        assert_eq!(iter.count(), 3);
+
+        let mut empty =
+            unsafe { RawValIter::<()>::from_raw_parts(NonNull::dangling().as_ptr(), 0) };
+        assert_eq!(empty.next_back(), None);
+    }
+
+    // Hands out the first buffer, then panics on any attempt to grow it.
+    struct NoGrowAllocator;
+
+    unsafe impl Allocator for NoGrowAllocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+
+        unsafe fn grow(
+            &self,
+            _ptr: NonNull<u8>,
+            _old_layout: Layout,
+            _new_layout: Layout,
+        ) -> Result<NonNull<u8>, AllocError> {
+            panic!("grow failed");
+        }
+    }
+
+    #[test]
+    fn push_survives_a_panicking_grow() {
+        let drops = Rc::new(Cell::new(0));
+        let mut v = Vector::with_capacity_in(2, NoGrowAllocator);
+        v.push(DropCounter(drops.clone()));
+        v.push(DropCounter(drops.clone()));
+
+        let result = catch_unwind(AssertUnwindSafe(|| v.push(DropCounter(drops.clone()))));
+        assert!(result.is_err());
+        // The rejected element was dropped exactly once; nothing else moved.
+        assert_eq!(drops.get(), 1);
+        assert_eq!((v.len(), v.capacity()), (2, 2));
+
+        // Still usable, and the two stored elements drop exactly once each.
+        assert!(v.pop().is_some());
+        v.push(DropCounter(drops.clone()));
+        assert_eq!(drops.get(), 2);
+        drop(v);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn insert_survives_a_panicking_grow() {
+        let mut v = Vector::with_capacity_in(3, NoGrowAllocator);
+        v.extend([1, 2, 3]);
+        let result = catch_unwind(AssertUnwindSafe(|| v.insert(0, 0)));
+        assert!(result.is_err());
+        assert_eq!(v, [1, 2, 3]);
+    }
+
+    #[test]
+    fn into_vec_keeps_the_buffer() {
+        let mut v: Vector<String> = Vector::with_capacity(4);
+        v.extend(["a", "b", "c"].map(String::from));
+        let ptr = v.as_ptr();
+
+        let mut vec = v.into_vec();
+        assert_eq!((vec.as_ptr(), vec.capacity()), (ptr, 4));
+        vec.push(String::from("d"));
+        vec.push(String::from("e"));
+        assert_eq!(vec, ["a", "b", "c", "d", "e"]);
+
+        assert_eq!(Vector::<i32>::new().into_vec(), []);
+    }
+
+    #[test]
+    fn repeat() {
+        let v = vector![1, 2];
+        let repeated = v.repeat(3);
+        assert_eq!(repeated, [1, 2, 1, 2, 1, 2]);
+        assert_eq!(repeated.capacity(), 6);
+        assert_eq!(v.repeat(5).len(), 10);
+        assert!(v.repeat(0).is_empty());
+        assert!(Vector::<u8>::new().repeat(7).is_empty());
+
+        assert!(catch_unwind(|| vector![1u8, 2].repeat(usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn starts_and_ends_with() {
+        let v = vector![1, 2, 3];
+        assert!(v.starts_with(&[1, 2]));
+        assert!(v.ends_with(&[2, 3]));
+        assert!(v.starts_with(&[]) && v.ends_with(&[]));
+        assert!(!v.starts_with(&[2]));
+        assert!(!v.ends_with(&[0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn split_first_and_last_owned() {
+        let (first, rest) = vector![1, 2, 3].split_first_owned().unwrap();
+        assert_eq!((first, rest), (1, vector![2, 3]));
+
+        let (last, rest) = vector![1, 2, 3].split_last_owned().unwrap();
+        assert_eq!((last, rest), (3, vector![1, 2]));
+
+        let (only, rest) = vector![String::from("a")].split_first_owned().unwrap();
+        assert_eq!(only, "a");
+        assert!(rest.is_empty());
+
+        assert!(Vector::<i32>::new().split_first_owned().is_none());
+        assert!(Vector::<i32>::new().split_last_owned().is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extend_from_reader_in_small_chunks() {
+        // Hands out at most three bytes per call, split by an `Interrupted`.
+        struct Trickle<'a>(&'a [u8], bool);
+
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.1 = !self.1;
+                if self.1 {
+                    return Err(std::io::ErrorKind::Interrupted.into());
+                }
+                let n = min(min(3, buf.len()), self.0.len());
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let data: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+        let mut v: Vector<u8> = vector![1, 2];
+        let read = v.extend_from_reader(&mut Trickle(&data, false)).unwrap();
+        assert_eq!(read, 10_000);
+        assert_eq!(v[..2], [1, 2]);
+        assert_eq!(v[2..], data[..]);
+    }
+
+    #[test]
+    fn shrink_policy_ignores_drains_that_remove_nothing() {
+        let mut v: Vector<i32> = Vector::with_capacity(100);
+        v.set_shrink_policy(ShrinkPolicy::Quarter);
+        v.extend([1, 2, 3]);
+        v.drain(0..0);
+        assert_eq!(v.capacity(), 100);
+
+        // A splice that grows the vector isn't a removal either.
+        v.splice(1..2, [7, 8, 9, 10]);
+        assert_eq!(v, [1, 7, 8, 9, 10, 3]);
+        assert_eq!(v.capacity(), 100);
+
+        // One that shrinks it is.
+        v.splice(1..5, [0]);
+        assert_eq!(v, [1, 0, 3]);
+        assert_eq!(v.capacity(), 6);
     }
 }
@@ -0,0 +1,35 @@
+//! Builds against the crate with only `core` and `alloc` in scope, so it
+//! catches any `std`-only item creeping into the default API. Run it with
+//! `cargo test --no-default-features` to check the `no_std` build as well.
+
+#![no_std]
+
+extern crate alloc;
+
+use vector::{vector, Vector};
+
+#[test]
+fn push_pop_without_std() {
+    let mut v: Vector<u32> = Vector::new();
+    for i in 0..10 {
+        v.push(i);
+    }
+    assert_eq!(v.len(), 10);
+    assert_eq!(v.pop(), Some(9));
+
+    v.retain(|x| x % 3 == 0);
+    assert_eq!(v, [0, 3, 6]);
+    assert_eq!(v, vector![0, 3, 6]);
+    assert_eq!(vector![7u8; 2], [7, 7]);
+}
+
+#[test]
+fn owned_elements_without_std() {
+    use alloc::string::{String, ToString};
+
+    let mut v: Vector<String> = Vector::with_capacity(2);
+    v.push("no".to_string());
+    v.push("std".to_string());
+    let joined: String = v.into_iter().collect();
+    assert_eq!(joined, "nostd");
+}